@@ -0,0 +1,270 @@
+//! The unit sets of the `conversions` directory, compiled in with the `bundled` feature
+
+use std::path::{Path, PathBuf};
+
+/// Every bundled file with its name, config files end with `.toml`
+#[cfg(feature = "bundled")]
+const FILES: &[(&str, &str)] = &[
+    ("angle", include_str!("../conversions/angle")),
+    ("angle.toml", include_str!("../conversions/angle.toml")),
+    ("area", include_str!("../conversions/area")),
+    ("area.toml", include_str!("../conversions/area.toml")),
+    ("cooking", include_str!("../conversions/cooking")),
+    ("cooking.toml", include_str!("../conversions/cooking.toml")),
+    ("data", include_str!("../conversions/data")),
+    ("data.toml", include_str!("../conversions/data.toml")),
+    ("distance", include_str!("../conversions/distance")),
+    (
+        "distance.toml",
+        include_str!("../conversions/distance.toml"),
+    ),
+    ("energy", include_str!("../conversions/energy")),
+    ("energy.toml", include_str!("../conversions/energy.toml")),
+    ("force", include_str!("../conversions/force")),
+    ("force.toml", include_str!("../conversions/force.toml")),
+    ("frequency", include_str!("../conversions/frequency")),
+    (
+        "frequency.toml",
+        include_str!("../conversions/frequency.toml"),
+    ),
+    ("fuel", include_str!("../conversions/fuel")),
+    ("fuel.toml", include_str!("../conversions/fuel.toml")),
+    ("mass", include_str!("../conversions/mass")),
+    ("mass.toml", include_str!("../conversions/mass.toml")),
+    ("power", include_str!("../conversions/power")),
+    ("power.toml", include_str!("../conversions/power.toml")),
+    ("pressure", include_str!("../conversions/pressure")),
+    (
+        "pressure.toml",
+        include_str!("../conversions/pressure.toml"),
+    ),
+    ("rate", include_str!("../conversions/rate")),
+    ("rate.toml", include_str!("../conversions/rate.toml")),
+    ("speed", include_str!("../conversions/speed")),
+    ("speed.toml", include_str!("../conversions/speed.toml")),
+    ("temperature", include_str!("../conversions/temperature")),
+    (
+        "temperature.toml",
+        include_str!("../conversions/temperature.toml"),
+    ),
+    ("time", include_str!("../conversions/time")),
+    ("time.toml", include_str!("../conversions/time.toml")),
+    ("volume", include_str!("../conversions/volume")),
+    ("volume.toml", include_str!("../conversions/volume.toml")),
+];
+
+#[cfg(not(feature = "bundled"))]
+const FILES: &[(&str, &str)] = &[];
+
+/// The content of a bundled file
+pub fn file(file_name: &str) -> Option<&'static str> {
+    FILES
+        .iter()
+        .find(|(name, _)| *name == file_name)
+        .map(|(_, content)| *content)
+}
+
+/// The names of all bundled unit sets
+pub fn sets() -> impl Iterator<Item = &'static str> {
+    FILES
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !name.ends_with(".toml"))
+}
+
+/// The path bundled files are shown with, they don't exist on disk
+pub fn path(file_name: &str) -> PathBuf {
+    Path::new("<bundled>").join(file_name)
+}
+
+/// The content of a bundled file by its [`path`]
+pub fn content(path: &Path) -> Option<&'static str> {
+    file(path.strip_prefix("<bundled>").ok()?.to_str()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Converter, Parsed};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::fs::read_to_string;
+
+    const SETS: [&str; 17] = [
+        "angle",
+        "area",
+        "cooking",
+        "data",
+        "distance",
+        "energy",
+        "force",
+        "frequency",
+        "fuel",
+        "mass",
+        "power",
+        "pressure",
+        "rate",
+        "speed",
+        "temperature",
+        "time",
+        "volume",
+    ];
+
+    fn read(file_name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("conversions")
+            .join(file_name);
+        read_to_string(path).unwrap()
+    }
+
+    /// A converter with all bundled sets, whose imports can refer to each other
+    fn converter() -> Converter {
+        SETS.into_iter().fold(Converter::new(), |converter, set| {
+            converter.with_str(set, read(set), Some(read(&format!("{set}.toml"))))
+        })
+    }
+
+    /// Whether `a` differs from `b` by at most a billionth of `b`
+    fn approx_eq(a: Decimal, b: Decimal) -> bool {
+        (a - b).abs() <= b.abs() * dec!(0.000000001)
+    }
+
+    #[test]
+    fn test_reference_values() {
+        let cases = [
+            ("angle", dec!(1), "turn", "rad", dec!(6.283185307179586)),
+            ("angle", dec!(90), "deg", "grad", dec!(100)),
+            ("area", dec!(1), "ac", "ha", dec!(0.40468564224)),
+            ("area", dec!(1), "mi2", "km2", dec!(2.589988110336)),
+            ("cooking", dec!(1), "cup", "ml", dec!(236.5882365)),
+            ("cooking", dec!(1), "tbsp", "pinch", dec!(48)),
+            ("energy", dec!(1), "kwh", "mj", dec!(3.6)),
+            ("energy", dec!(1), "kcal", "btu", dec!(3.965666831)),
+            ("energy", dec!(1), "j", "ev", dec!(6241509074460762607.776)),
+            ("force", dec!(1), "kgf", "lbf", dec!(2.204622621848776)),
+            ("frequency", dec!(3000), "rpm", "hz", dec!(50)),
+            ("frequency", dec!(2.4), "ghz", "mhz", dec!(2400)),
+            ("fuel", dec!(30), "mpg", "l100km", dec!(7.840486111)),
+            ("fuel", dec!(5), "l100km", "impmpg", dec!(56.49618727)),
+            ("mass", dec!(1), "st", "kg", dec!(6.35029318)),
+            ("mass", dec!(1), "kg", "oz", dec!(35.27396194958041)),
+            ("power", dec!(1), "hp", "ps", dec!(1.013869665424)),
+            ("power", dec!(1), "kw", "btuh", dec!(3412.141633127942)),
+            ("pressure", dec!(1), "atm", "psi", dec!(14.69594877551345)),
+            ("pressure", dec!(760), "torr", "mmhg", dec!(759.9998917)),
+            ("rate", dec!(1), "gbps", "kbps", dec!(1000000)),
+            ("speed", dec!(100), "kmh", "mph", dec!(62.13711922373339)),
+            ("speed", dec!(10), "kn", "mps", dec!(5.144444444444444)),
+            ("time", dec!(1), "wk", "min", dec!(10080)),
+            ("time", dec!(1), "yr", "d", dec!(365.25)),
+            ("volume", dec!(1), "gal", "floz", dec!(128)),
+            ("volume", dec!(1), "impgal", "gal", dec!(1.200949925504)),
+            ("volume", dec!(1), "ft3", "l", dec!(28.316846592)),
+        ];
+
+        for (set, value, start, end, expected) in cases {
+            let parsed = Parsed::try_new(&read(set)).unwrap();
+            let converted = parsed.convert(start, end, value).unwrap();
+            assert!(
+                approx_eq(converted, expected),
+                "{value}{start} are {converted}{end}, not {expected}{end}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for set in SETS {
+            let parsed = Parsed::try_new(&read(set)).unwrap();
+            assert_eq!(parsed.components().len(), 1, "{set} isn't connected");
+            assert_eq!(parsed.one_way_conversions(), vec![], "{set}");
+
+            // Every conversion with an inverse has to undo it. Decimals have a fixed number of
+            // decimal places, so the round trip starts with the unit whose values are smaller to
+            // not lose precision.
+            let conversions = parsed.conversions();
+            let inverted = conversions.iter().filter(|(origin, dest, _)| {
+                conversions.iter().any(|(inverse_origin, inverse_dest, _)| {
+                    inverse_origin == dest && inverse_dest == origin
+                })
+            });
+            let x = dec!(3.7);
+            for &(origin, dest, _) in inverted {
+                let (start, end) = if parsed.convert(origin, dest, x).unwrap() >= x {
+                    (origin, dest)
+                } else {
+                    (dest, origin)
+                };
+                let there = parsed.convert(start, end, x).unwrap();
+                let back = parsed.convert(end, start, there).unwrap();
+                assert!(approx_eq(back, x), "{x}{start} are {back}{start} via {end}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_aliases() {
+        let converter = converter();
+        for set in SETS {
+            let loaded = converter
+                .load(set)
+                .unwrap_or_else(|err| panic!("{set}: {err}"));
+            for name in loaded.unit_names() {
+                assert!(loaded.contains(name), "{name} of {set} isn't a unit");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compound_units() {
+        let converter = converter();
+        let cases = [
+            (dec!(1), "kw*h", "j", dec!(3600000)),
+            (dec!(100), "km/h", "m/s", dec!(27.77777777777778)),
+            (dec!(1), "n*m", "j", dec!(1)),
+            (dec!(2), "l", "cm^3", dec!(2000)),
+            (dec!(1), "kg*m/s^2", "n", dec!(1)),
+            (dec!(50), "hz", "1/min", dec!(3000)),
+            (dec!(100), "mbit/s", "mb/min", dec!(715.2557373046875)),
+        ];
+
+        for (value, start, end, expected) in cases {
+            let converted = converter.convert_compound(value, start, end).unwrap();
+            assert!(
+                approx_eq(converted, expected),
+                "{value}{start} are {converted}{end}, not {expected}{end}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "bundled")]
+    fn test_bundled_files() {
+        assert_eq!(sets().collect::<Vec<_>>(), SETS);
+        for set in SETS {
+            assert_eq!(file(set), Some(read(set).as_str()));
+            let config = format!("{set}.toml");
+            assert_eq!(file(&config), Some(read(&config).as_str()));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "bundled")]
+    fn test_bundled_lookup() {
+        let paths = crate::file_paths("distance.toml");
+        assert_eq!(paths.last(), Some(&path("distance.toml")));
+        assert_eq!(
+            crate::read_file(&path("distance.toml")).unwrap(),
+            read("distance.toml")
+        );
+
+        let dir = std::env::temp_dir().join(format!("manada-bundled-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lengths"), "@include distance\nunit league").unwrap();
+        let converter = Converter::new().with_file(dir.join("lengths"));
+        let lengths = converter.load("lengths").ok().unwrap();
+        assert_eq!(lengths.convert(dec!(3), "km", "m"), Ok(dec!(3000)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -17,7 +17,17 @@ pub enum Operator {
     Div,
 }
 
-#[derive(PartialEq, Debug, From)]
+impl Operator {
+    /// Operators with a higher precedence bind stronger
+    pub fn precedence(self) -> u8 {
+        match self {
+            Self::Add | Self::Sub => 1,
+            Self::Mul | Self::Div => 2,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, From)]
 pub enum Value {
     Var,
     #[from]
@@ -35,7 +45,18 @@ impl Display for Value {
             Self::Var => write!(f, "x"),
             Self::Num(n) => write!(f, "{}", n.normalize()),
             Self::Calc { left, op, right } => {
-                if let Self::Calc { .. } = **left {
+                // The parser is left associative, so a left operand with the same precedence
+                // doesn't need parentheses but a right one does
+                let precedence = |value: &Value| match value {
+                    Self::Calc { op, .. } => Some(op.precedence()),
+                    _ => None,
+                };
+                let left_needs_parentheses =
+                    precedence(left).is_some_and(|left| left < op.precedence());
+                let right_needs_parentheses =
+                    precedence(right).is_some_and(|right| right <= op.precedence());
+
+                if left_needs_parentheses {
                     write!(f, "({})", left)?;
                 } else {
                     write!(f, "{}", left)?;
@@ -43,7 +64,7 @@ impl Display for Value {
 
                 write!(f, " {} ", op)?;
 
-                if let Self::Calc { .. } = **right {
+                if right_needs_parentheses {
                     write!(f, "({})", right)
                 } else {
                     write!(f, "{}", right)
@@ -53,28 +74,250 @@ impl Display for Value {
     }
 }
 
+/// The product of `a` and `b` if it can be calculated without rounding
+fn exact_mul(a: Decimal, b: Decimal) -> Option<Decimal> {
+    let product = a.checked_mul(b)?;
+    (product.scale() == a.scale() + b.scale()).then_some(product)
+}
+
+/// Whether `x * a * b` results in the same as `x * (a * b)` for every x. Multiplying by integers
+/// only rounds away the digits which don't fit, and a power of ten only appends zeros to them,
+/// so both round the same digits. In any other case `x * a` can round differently.
+fn is_mergeable(a: Decimal, b: Decimal) -> bool {
+    let is_power_of_ten = |n: Decimal| {
+        let mantissa = n.mantissa().unsigned_abs();
+        mantissa != 0 && 10u128.pow(mantissa.ilog10()) == mantissa
+    };
+    a.scale() == 0 && b.scale() == 0 && (is_power_of_ten(a) || is_power_of_ten(b))
+}
+
+/// Calculates `left op right`, `None` if it overflows or divides by zero
+fn checked(left: Decimal, op: Operator, right: Decimal) -> Option<Decimal> {
+    match op {
+        Operator::Add => left.checked_add(right),
+        Operator::Sub => left.checked_sub(right),
+        Operator::Mul => left.checked_mul(right),
+        Operator::Div => left.checked_div(right),
+    }
+}
+
 impl Value {
+    pub(crate) fn calc(left: Value, op: Operator, right: Value) -> Value {
+        Self::Calc {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    /// Folds constant calculations, merges chained multiplications by constants
+    /// (`x * a * b` to `x * (a * b)`) and removes identities like `x * 1` or `x + 0`, without
+    /// changing the results of [`Value::evaluate`]
+    ///
+    /// Calculations which overflow are kept. Chained multiplications are only merged if both
+    /// factors are integers and one of them is a power of ten, and chained divisions never are,
+    /// as `x / a / b` and `x / (a * b)` can round differently.
+    pub fn simplify(self) -> Value {
+        let Self::Calc { left, op, right } = self else {
+            return self;
+        };
+
+        match (left.simplify(), op, right.simplify()) {
+            (Self::Num(left), op, Self::Num(right)) => checked(left, op, right)
+                .map_or_else(|| Self::calc(left.into(), op, right.into()), Self::Num),
+            (value, Operator::Mul | Operator::Div, Self::Num(n)) if n == Decimal::ONE => value,
+            (value, Operator::Add | Operator::Sub, Self::Num(n)) if n.is_zero() => value,
+            (Self::Num(n), Operator::Mul, value) if n == Decimal::ONE => value,
+            (Self::Num(n), Operator::Add, value) if n.is_zero() => value,
+            (
+                Self::Calc {
+                    left: inner_left,
+                    op: inner_op,
+                    right: inner_right,
+                },
+                Operator::Mul,
+                Self::Num(b),
+            ) if inner_op == Operator::Mul => {
+                if let Self::Num(a) = *inner_right
+                    && is_mergeable(a, b)
+                    && let Some(product) = exact_mul(a, b)
+                {
+                    return Self::calc(*inner_left, Operator::Mul, Self::Num(product)).simplify();
+                }
+                Self::calc(
+                    Self::Calc {
+                        left: inner_left,
+                        op: inner_op,
+                        right: inner_right,
+                    },
+                    Operator::Mul,
+                    Self::Num(b),
+                )
+            }
+            (left, op, right) => Self::calc(left, op, right),
+        }
+    }
+
     pub fn evaluate(&self, x: Decimal) -> Option<Decimal> {
         match self {
             Self::Var => Some(x),
             Self::Num(n) => Some(*n),
+            Self::Calc { left, right, op } => checked(left.evaluate(x)?, *op, right.evaluate(x)?),
+        }
+    }
+
+    /// The bounds of the results for every x from `low` to `high`, using interval arithmetic.
+    /// They are exact if x occurs only once, otherwise they can be wider than the results, e.g.
+    /// `x * x` from -1 to 1 results in -1 to 1. Fails if a divisor can be zero.
+    pub fn evaluate_interval(&self, low: Decimal, high: Decimal) -> Option<(Decimal, Decimal)> {
+        match self {
+            Self::Var => Some((low, high)),
+            Self::Num(n) => Some((*n, *n)),
             Self::Calc { left, right, op } => {
-                let left = left.evaluate(x)?;
-                let right = right.evaluate(x)?;
+                let (left_low, left_high) = left.evaluate_interval(low, high)?;
+                let (right_low, right_high) = right.evaluate_interval(low, high)?;
                 match op {
-                    Operator::Add => Some(left + right),
-                    Operator::Sub => Some(left - right),
-                    Operator::Mul => Some(left * right),
-                    Operator::Div => (!right.is_zero()).then(|| left / right),
+                    Operator::Add => Some((
+                        left_low.checked_add(right_low)?,
+                        left_high.checked_add(right_high)?,
+                    )),
+                    Operator::Sub => Some((
+                        left_low.checked_sub(right_high)?,
+                        left_high.checked_sub(right_low)?,
+                    )),
+                    Operator::Mul | Operator::Div => {
+                        let divides_by_zero = *op == Operator::Div
+                            && right_low <= Decimal::ZERO
+                            && right_high >= Decimal::ZERO;
+                        if divides_by_zero {
+                            return None;
+                        }
+                        let calculate = |a: Decimal, b: Decimal| match op {
+                            Operator::Mul => a.checked_mul(b),
+                            _ => a.checked_div(b),
+                        };
+                        let bounds = [
+                            calculate(left_low, right_low)?,
+                            calculate(left_low, right_high)?,
+                            calculate(left_high, right_low)?,
+                            calculate(left_high, right_high)?,
+                        ];
+                        Some((*bounds.iter().min()?, *bounds.iter().max()?))
+                    }
                 }
             }
         }
     }
+
+    /// Evaluates the calculation and its derivative with respect to x at `x`
+    pub fn evaluate_with_derivative(&self, x: Decimal) -> Option<(Decimal, Decimal)> {
+        match self {
+            Self::Var => Some((x, Decimal::ONE)),
+            Self::Num(n) => Some((*n, Decimal::ZERO)),
+            Self::Calc { left, right, op } => {
+                let (left, d_left) = left.evaluate_with_derivative(x)?;
+                let (right, d_right) = right.evaluate_with_derivative(x)?;
+                let value = checked(left, *op, right)?;
+                let derivative = match op {
+                    Operator::Add | Operator::Sub => checked(d_left, *op, d_right)?,
+                    Operator::Mul => d_left
+                        .checked_mul(right)?
+                        .checked_add(left.checked_mul(d_right)?)?,
+                    Operator::Div => d_left
+                        .checked_mul(right)?
+                        .checked_sub(left.checked_mul(d_right)?)?
+                        .checked_div(right.checked_mul(right)?)?,
+                };
+                Some((value, derivative))
+            }
+        }
+    }
+
+    /// The simplified derivative with respect to x. Products with a zero derivative are left out,
+    /// so it can be defined where the calculation isn't.
+    pub fn derivative(&self) -> Value {
+        let Self::Calc { left, op, right } = self else {
+            let slope = if *self == Self::Var { 1 } else { 0 };
+            return Self::Num(Decimal::from(slope));
+        };
+        let (left, right) = (*left.clone(), *right.clone());
+        let (d_left, d_right) = (left.derivative(), right.derivative());
+        let is_zero = |value: &Value| matches!(value, Self::Num(n) if n.is_zero());
+        let product = |a: Value, b: Value| {
+            if is_zero(&a) || is_zero(&b) {
+                Self::Num(Decimal::ZERO)
+            } else {
+                Self::calc(a, Operator::Mul, b)
+            }
+        };
+
+        match op {
+            Operator::Add | Operator::Sub => Self::calc(d_left, *op, d_right),
+            // The product rule (l * r)' = l' * r + l * r'
+            Operator::Mul => Self::calc(
+                product(d_left, right.clone()),
+                Operator::Add,
+                product(left, d_right),
+            ),
+            Operator::Div if is_zero(&d_left) && is_zero(&d_right) => Self::Num(Decimal::ZERO),
+            Operator::Div if is_zero(&d_right) => Self::calc(d_left, Operator::Div, right),
+            // The quotient rule (l / r)' = (l' * r - l * r') / (r * r)
+            Operator::Div => Self::calc(
+                Self::calc(
+                    product(d_left, right.clone()),
+                    Operator::Sub,
+                    product(left, d_right),
+                ),
+                Operator::Div,
+                Self::calc(right.clone(), Operator::Mul, right),
+            ),
+        }
+        .simplify()
+    }
+
+    /// Classifies how the result depends on x, using the derivative
+    pub fn linearity(&self) -> Linearity {
+        if self.derivative().contains_var() {
+            Linearity::Nonlinear
+        } else if self.evaluate(Decimal::ZERO) == Some(Decimal::ZERO) {
+            Linearity::Linear
+        } else {
+            Linearity::Affine
+        }
+    }
+
+    /// Whether the results only increase or only decrease for every x from `low` to `high`, which
+    /// is known if the sign of the derivative can't change in between
+    pub fn is_monotonic(&self, low: Decimal, high: Decimal) -> bool {
+        self.derivative()
+            .evaluate_interval(low, high)
+            .is_some_and(|(min, max)| min >= Decimal::ZERO || max <= Decimal::ZERO)
+    }
+
+    fn contains_var(&self) -> bool {
+        match self {
+            Self::Var => true,
+            Self::Num(_) => false,
+            Self::Calc { left, right, .. } => left.contains_var() || right.contains_var(),
+        }
+    }
+}
+
+/// How a calculation depends on x
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Linearity {
+    /// `a * x`, which maps zero to zero
+    Linear,
+    /// `a * x + b`
+    Affine,
+    /// Anything else, e.g. `100 / x`
+    Nonlinear,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parser::Parser;
     use rust_decimal_macros::dec;
 
     #[test]
@@ -101,6 +344,159 @@ mod tests {
         assert!(my_expr.evaluate(dec!(3.0)).is_none());
     }
 
+    #[test]
+    fn test_evaluate_with_derivative() {
+        let cases = [
+            ("x * 1000", dec!(2), dec!(2000), dec!(1000)),
+            ("(x - 32) * 5 / 9", dec!(212), dec!(100), dec!(5) / dec!(9)),
+            ("100 / x", dec!(4), dec!(25), dec!(-6.25)),
+            ("x * x + 1", dec!(3), dec!(10), dec!(6)),
+        ];
+        for (calculation, x, value, derivative) in cases {
+            let parsed = Parser::new(token::token_list(calculation).unwrap())
+                .parse_expression()
+                .unwrap();
+            assert_eq!(
+                parsed.evaluate_with_derivative(x),
+                Some((value, derivative)),
+                "{calculation}"
+            );
+        }
+        assert_eq!(
+            Value::calc(Value::Num(dec!(1)), Operator::Div, Value::Var)
+                .evaluate_with_derivative(dec!(0)),
+            None
+        );
+    }
+
+    fn parse(calculation: &str) -> Value {
+        Parser::new(token::token_list(calculation).unwrap())
+            .parse_expression()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_derivative() {
+        let cases = [
+            ("x * 1000", "1000"),
+            ("x / 1000", "0.001"),
+            ("(x - 32) * 5 / 9", "0.5555555555555555555555555556"),
+            ("x + 273.15", "1"),
+            ("100 / x", "-100 / (x * x)"),
+            ("x * x + 1", "x + x"),
+            ("2 * (x - 1) * 3", "6"),
+            ("42", "0"),
+        ];
+        for (calculation, derivative) in cases {
+            assert_eq!(
+                parse(calculation).derivative().to_string(),
+                derivative,
+                "{calculation}"
+            );
+        }
+        assert_eq!(
+            parse("100 / x").derivative().evaluate(dec!(4)),
+            Some(dec!(-6.25))
+        );
+    }
+
+    #[test]
+    fn test_linearity() {
+        assert_eq!(parse("x * 1000").linearity(), Linearity::Linear);
+        assert_eq!(parse("x / 3.6").linearity(), Linearity::Linear);
+        assert_eq!(parse("(x - 32) * 5 / 9").linearity(), Linearity::Affine);
+        assert_eq!(parse("100 / x").linearity(), Linearity::Nonlinear);
+        assert_eq!(parse("x * x").linearity(), Linearity::Nonlinear);
+    }
+
+    #[test]
+    fn test_is_monotonic() {
+        assert!(parse("(x - 32) * 5 / 9").is_monotonic(dec!(-40), dec!(212)));
+        assert!(parse("100 / x").is_monotonic(dec!(5), dec!(10)));
+        assert!(!parse("100 / x").is_monotonic(dec!(-5), dec!(5)));
+        assert!(parse("x * x").is_monotonic(dec!(1), dec!(2)));
+        assert!(!parse("x * x").is_monotonic(dec!(-1), dec!(2)));
+    }
+
+    #[test]
+    fn test_evaluate_interval() {
+        let cases = [
+            ("x * 1000", dec!(1), dec!(2), Some((dec!(1000), dec!(2000)))),
+            ("x * -2", dec!(1), dec!(2), Some((dec!(-4), dec!(-2)))),
+            (
+                "x * 9 / 5 + 32",
+                dec!(-40),
+                dec!(20),
+                Some((dec!(-40), dec!(68))),
+            ),
+            ("100 / x", dec!(5), dec!(10), Some((dec!(10), dec!(20)))),
+            ("100 / x", dec!(-1), dec!(1), None),
+            ("x * x", dec!(-1), dec!(1), Some((dec!(-1), dec!(1)))),
+        ];
+        for (calculation, low, high, bounds) in cases {
+            let parsed = Parser::new(token::token_list(calculation).unwrap())
+                .parse_expression()
+                .unwrap();
+            assert_eq!(parsed.evaluate_interval(low, high), bounds, "{calculation}");
+        }
+    }
+
+    #[test]
+    fn test_simplify() {
+        let cases = [
+            (
+                "x / (1048576 * 1048576 * 1048576 * 8388608)",
+                "x / 9671406556917033397649408",
+            ),
+            ("x * 1024 * 1000", "x * 1024000"),
+            ("x * 1000 * -3 * 10", "x * -30000"),
+            ("x * 1024 * 1024", "x * 1024 * 1024"),
+            ("x / 12 / 3", "x / 12 / 3"),
+            ("x * 1 + 0", "x"),
+            ("1 * x / 1 - 0", "x"),
+            ("0 + x * (2 - 1)", "x"),
+            ("(x - 32) * 5 / 9", "(x - 32) * 5 / 9"),
+            ("x * 9 / 5 + 32", "x * 9 / 5 + 32"),
+            ("x * 0.5 * 0.2", "x * 0.5 * 0.2"),
+            ("x * 1000.0 * 10", "x * 1000 * 10"),
+            ("x / (1 - 1)", "x / 0"),
+            (
+                "x * 79228162514264337593543950335 * 10",
+                "x * 79228162514264337593543950335 * 10",
+            ),
+            (
+                "79228162514264337593543950335 * 10 * x",
+                "79228162514264337593543950335 * 10 * x",
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let value = Parser::new(token::token_list(input).unwrap())
+                .parse_expression()
+                .unwrap();
+            let simplified = value.clone().simplify();
+            assert_eq!(simplified.to_string(), expected);
+
+            for x in [
+                dec!(0),
+                dec!(1),
+                dec!(-3.5),
+                dec!(1024),
+                dec!(0.001),
+                dec!(123456789),
+                dec!(0.1234567890123456789012345678),
+                dec!(-7.922816251426433759354395033),
+                dec!(12345678901234567890.12345678),
+            ] {
+                assert_eq!(
+                    value.evaluate(x),
+                    simplified.evaluate(x),
+                    "{input} with x = {x}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_format() {
         let my_expr = Value::Calc {
@@ -115,6 +511,13 @@ mod tests {
             op: Operator::Add,
             right: Box::new(Value::Var),
         };
-        assert_eq!(format!("{}", my_expr), String::from("(1 + x) + x"));
+        assert_eq!(format!("{}", my_expr), String::from("1 + x + x"));
+
+        let my_expr = Value::Calc {
+            left: Box::new(Value::Var),
+            op: Operator::Sub,
+            right: Box::new(my_expr),
+        };
+        assert_eq!(format!("{}", my_expr), String::from("x - (1 + x + x)"));
     }
 }
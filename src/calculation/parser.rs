@@ -9,6 +9,8 @@ pub enum CalculationParseError {
     UnexpectedToken(Token),
 }
 
+impl std::error::Error for CalculationParseError {}
+
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
@@ -113,11 +115,11 @@ mod tests {
     #[test]
     fn test_operator_precedence() {
         let value = parse_ok("1 + 2 * 3");
-        assert_eq!(format!("{}", value), "1 + (2 * 3)");
+        assert_eq!(format!("{}", value), "1 + 2 * 3");
         assert_eq!(value.evaluate(dec!(0.0)), Some(dec!(7.0)));
 
         let value = parse_ok("10 - 4 / 2");
-        assert_eq!(format!("{}", value), "10 - (4 / 2)");
+        assert_eq!(format!("{}", value), "10 - 4 / 2");
         assert_eq!(value.evaluate(dec!(0.0)), Some(dec!(8.0)));
     }
 
@@ -131,14 +133,14 @@ mod tests {
     #[test]
     fn test_variable_handling() {
         let value = parse_ok("x * 2 + 1");
-        assert_eq!(format!("{}", value), "(x * 2) + 1");
+        assert_eq!(format!("{}", value), "x * 2 + 1");
         assert_eq!(value.evaluate(dec!(3.0)), Some(dec!(7.0)));
     }
 
     #[test]
     fn test_nested_expressions() {
         let value = parse_ok("x * (1 + 2 * x)");
-        assert_eq!(format!("{}", value), "x * (1 + (2 * x))");
+        assert_eq!(format!("{}", value), "x * (1 + 2 * x)");
         assert_eq!(value.evaluate(dec!(2.0)), Some(dec!(10.0)));
     }
 
@@ -157,6 +159,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_minimal_parentheses() {
+        let cases = [
+            ("(x * 2) + 1", "x * 2 + 1"),
+            ("((x - 32) * 5) / 9", "(x - 32) * 5 / 9"),
+            (
+                "x / (1048576 * 1048576 * 8388608)",
+                "x / (1048576 * 1048576 * 8388608)",
+            ),
+            ("x - (1 - 2)", "x - (1 - 2)"),
+            ("x + (1 + 2)", "x + (1 + 2)"),
+            ("x * (2 / 3)", "x * (2 / 3)"),
+            ("(x + 1) * (x - 1)", "(x + 1) * (x - 1)"),
+            ("x - -5", "x - -5"),
+            ("((x))", "x"),
+        ];
+
+        for (input, expected) in cases {
+            let value = parse_ok(input);
+            let formatted = value.to_string();
+            assert_eq!(formatted, expected);
+            assert_eq!(parse_ok(&formatted), value, "{formatted} doesn't re-parse");
+        }
+    }
+
     #[test]
     fn test_single_expr() {
         assert_eq!(parse_ok("42").evaluate(dec!(0.0)), Some(dec!(42.0)));
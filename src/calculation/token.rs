@@ -28,6 +28,8 @@ pub enum TokenizeError {
     },
 }
 
+impl std::error::Error for TokenizeError {}
+
 pub fn token_list(unparsed: &str) -> Result<Vec<Token>, TokenizeError> {
     let serialized = unparsed.replace(' ', "");
 
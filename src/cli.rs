@@ -1,32 +1,205 @@
-use clap::Parser;
-use rust_decimal::Decimal;
+use clap::{Command as ClapCommand, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::{ffi::OsString, path::PathBuf};
 
 #[derive(Parser)]
+#[command(
+    override_usage = "manada [UNIT_SET] <VALUE> <DESTINATION>\n       manada <COMMAND>",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
-    pub unit_set: String,
-    #[arg(value_parser = value_parser)]
-    pub value: Value,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Match units ignoring their case and Unicode normalization
+    #[arg(short, long, global = true)]
+    pub ignore_case: bool,
+    /// Print the name of the unit, e.g. "2 miles" instead of "2mi"
+    #[arg(short, long)]
+    pub long: bool,
+    /// Print the name of the unit in this language and format the number accordingly, e.g. "de"
+    #[arg(long)]
+    pub locale: Option<String>,
+    /// Round the result to this many decimal places, defaults to 4 with --long and --locale
+    #[arg(short, long)]
+    pub precision: Option<u32>,
+    /// The unit set, the value and the destination unit. The unit set is inferred from the units
+    /// if it is omitted
+    #[arg(
+        num_args = 2..=3,
+        required = true,
+        value_names = ["UNIT_SET", "VALUE", "DESTINATION"],
+    )]
+    args: Vec<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List all unit sets and the files they are merged from, the file with the highest priority
+    /// is marked with a *
+    List {
+        #[arg(long)]
+        json: bool,
+    },
+    /// List all units of a unit set with their aliases and conversion counts
+    Units {
+        unit_set: String,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check unit sets for problems like unknown units in aliases or disconnected units, all
+    /// sets are checked if none is given. Exits with an error if there are errors
+    Lint {
+        unit_set: Option<String>,
+        /// Also fail if there are warnings
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Format conversion files in place
+    Fmt {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Only check whether the files are formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print a completion script, which also completes unit sets and units
+    Completions { shell: Shell },
+    /// The partial command line to complete, used by the completion scripts
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+        words: Vec<String>,
+    },
+}
+
+pub struct Conversion {
+    pub unit_set: Option<String>,
+    pub value: String,
     pub destination: String,
 }
 
-#[derive(Clone)]
-pub struct Value {
-    pub value: Decimal,
-    pub unit: String,
+impl Cli {
+    /// Parses the arguments of the process, see [`Cli::parse_from_args`]
+    pub fn parse_args() -> Self {
+        Self::parse_from_args(std::env::args_os())
+    }
+
+    /// Parses the arguments after moving the options in front of a conversion, so they can follow
+    /// values like `-40c`, which would otherwise be read as options
+    pub fn parse_from_args(args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        Self::parse_from(reorder(args.into_iter().map(Into::into).collect()))
+    }
+
+    pub fn conversion(self) -> Conversion {
+        let mut args = self.args.into_iter();
+        let unit_set = (args.len() == 3).then(|| args.next()).flatten();
+        let (Some(value), Some(destination)) = (args.next(), args.next()) else {
+            unreachable!("clap ensures there are at least two arguments")
+        };
+
+        Conversion {
+            unit_set,
+            value,
+            destination,
+        }
+    }
+}
+
+/// Moves the options of a conversion in front of a `--`, which is followed by the unit set, the
+/// value and the destination. Subcommands and arguments which already contain a `--` are left as
+/// they are.
+fn reorder(args: Vec<OsString>) -> Vec<OsString> {
+    let command = Cli::command();
+    let Some((program, rest)) = args.split_first() else {
+        return args;
+    };
+
+    let mut options = vec![program.clone()];
+    let mut positionals = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        let text = arg.to_str().unwrap_or_default();
+        if text == "--" {
+            return args;
+        }
+        // Negative values like -40c and -.5km aren't options
+        let is_option = text
+            .strip_prefix('-')
+            .and_then(|option| option.chars().next())
+            .is_some_and(|c| !c.is_ascii_digit() && c != '.');
+        if is_option {
+            options.push(arg.clone());
+            if needs_value(&command, text) {
+                options.extend(rest.next().cloned());
+            }
+        } else if positionals.is_empty() && command.find_subcommand(text).is_some() {
+            return args;
+        } else {
+            positionals.push(arg.clone());
+        }
+    }
+
+    options.push(OsString::from("--"));
+    options.extend(positionals);
+    options
 }
 
-fn value_parser(s: &str) -> Result<Value, String> {
-    let mut unit = String::new();
-    for char in s.chars().rev() {
-        if char.is_ascii_digit() {
-            break;
+/// Whether an option like `-p` or `--locale` is followed by its value in the next argument,
+/// unlike `-p2` and `--locale=de`
+fn needs_value(command: &ClapCommand, option: &str) -> bool {
+    let mut valued = command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && arg.get_action().takes_values());
+    match option.strip_prefix("--") {
+        Some(long) => !long.contains('=') && valued.any(|arg| arg.get_long() == Some(long)),
+        None => {
+            // Short options can be combined like `-ip 2`, the first one taking a value ends them
+            let shorts = option.trim_start_matches('-');
+            let shorts_with_values = valued.filter_map(|arg| arg.get_short()).collect::<Vec<_>>();
+            shorts
+                .char_indices()
+                .find(|(_, short)| shorts_with_values.contains(short))
+                .is_some_and(|(i, short)| i + short.len_utf8() == shorts.len())
         }
-        unit = char.to_string() + &unit;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_after_values() {
+        let cli = Cli::parse_from_args(["manada", "distance", "2km", "mile", "--long", "-p", "2"]);
+        assert!(cli.long);
+        assert_eq!(cli.precision, Some(2));
+        let conversion = cli.conversion();
+        assert_eq!(conversion.unit_set.as_deref(), Some("distance"));
+        assert_eq!(conversion.value, "2km");
+        assert_eq!(conversion.destination, "mile");
 
-    let num_str = &s[..s.len() - unit.len()];
-    let num = num_str.parse();
-    let num = num.map_err(|_| format!("{num_str} isn't a number"))?;
+        let cli = Cli::parse_from_args(["manada", "-40c", "f", "-ip3", "--locale=de"]);
+        assert!(cli.ignore_case);
+        assert_eq!(cli.precision, Some(3));
+        assert_eq!(cli.locale.as_deref(), Some("de"));
+        let conversion = cli.conversion();
+        assert_eq!(conversion.unit_set, None);
+        assert_eq!(conversion.value, "-40c");
 
-    Ok(Value { unit, value: num })
+        let cli =
+            Cli::parse_from_args(["manada", "--locale", "de", "-.5..-0.2km", "m", "-ip", "1"]);
+        assert_eq!(cli.locale.as_deref(), Some("de"));
+        assert_eq!(cli.precision, Some(1));
+        assert_eq!(cli.conversion().value, "-.5..-0.2km");
+
+        let cli = Cli::parse_from_args(["manada", "--", "units", "5a", "b"]);
+        assert_eq!(cli.conversion().unit_set.as_deref(), Some("units"));
+
+        let cli = Cli::parse_from_args(["manada", "lint", "distance", "--strict"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Lint { strict: true, .. })
+        ));
+    }
 }
@@ -0,0 +1,180 @@
+use crate::cli::Cli;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use manada::{Converter, number_prefix};
+use std::{collections::BTreeSet, fs::read_dir, io};
+
+/// Unit sets with these names are only used for conversions after `--`
+pub const SUBCOMMANDS: [&str; 5] = ["list", "units", "lint", "fmt", "completions"];
+
+/// Prints the static completions generated by clap followed by a hook which asks
+/// `manada __complete` for unit sets and units
+pub fn print_script(shell: Shell) {
+    clap_complete::generate(shell, &mut Cli::command(), "manada", &mut io::stdout());
+
+    let hook = match shell {
+        Shell::Bash => BASH_HOOK,
+        Shell::Zsh => ZSH_HOOK,
+        Shell::Fish => FISH_HOOK,
+        _ => return,
+    };
+    println!("{hook}");
+}
+
+const BASH_HOOK: &str = r#"
+_manada_dynamic() {
+    if [[ "${COMP_WORDS[COMP_CWORD]}" == -* ]]; then
+        _manada "$@"
+        return
+    fi
+    local IFS=$'\n'
+    COMPREPLY=($(manada __complete "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null))
+}
+complete -F _manada_dynamic -o nosort -o bashdefault -o default manada"#;
+
+const ZSH_HOOK: &str = r#"
+_manada_dynamic() {
+    if [[ "${words[CURRENT]}" == -* ]]; then
+        _manada "$@"
+        return
+    fi
+    local -a candidates
+    candidates=("${(@f)$(manada __complete "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+    compadd -a candidates
+}
+compdef _manada_dynamic manada"#;
+
+const FISH_HOOK: &str = r#"
+complete -c manada -f -a '(manada __complete (commandline -opc)[2..-1] (commandline -ct) 2>/dev/null)'"#;
+
+/// The candidates for the last word of a partial command line, `words` doesn't contain the
+/// program name
+pub fn candidates(converter: &Converter, words: &[String]) -> Vec<String> {
+    let Some((current, previous)) = words.split_last() else {
+        return Vec::new();
+    };
+
+    let candidates = match previous {
+        [] if number_prefix(current).is_some() => units_of_all(converter, Some(current)),
+        [] => SUBCOMMANDS
+            .into_iter()
+            .map(String::from)
+            .chain(converter.names().map(String::from))
+            .collect(),
+        [command] if command == "units" || command == "lint" => {
+            converter.names().map(String::from).collect()
+        }
+        [command] if command == "completions" => ["bash", "zsh", "fish", "elvish", "powershell"]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        [command, ..] if command == "fmt" => files(current),
+        // The other subcommands only take options after their first argument
+        [command, ..] if SUBCOMMANDS.contains(&command.as_str()) => Vec::new(),
+        [set] if converter.names().any(|name| name == set) => {
+            units_of(converter, set, Some(current))
+        }
+        [set, _] if converter.names().any(|name| name == set) => units_of(converter, set, None),
+        [_] => units_of_all(converter, None),
+        _ => Vec::new(),
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current.as_str()))
+        .collect()
+}
+
+/// The files and directories in the directory of a partially typed path
+fn files(current: &str) -> Vec<String> {
+    let dir = current.rfind('/').map_or("", |end| &current[..=end]);
+    let Ok(entries) = read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+    let show_hidden = current[dir.len()..].starts_with('.');
+
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            if name.starts_with('.') && !show_hidden {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir}{name}{slash}"))
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// All units and aliases of a set, prefixed with the number of `value` if there is one
+fn units_of(converter: &Converter, set: &str, value: Option<&str>) -> Vec<String> {
+    let Ok(set) = converter.load(set) else {
+        return Vec::new();
+    };
+    let prefix = value.and_then(number_prefix).unwrap_or_default();
+
+    set.unit_names()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|unit| format!("{prefix}{unit}"))
+        .collect()
+}
+
+fn units_of_all(converter: &Converter, value: Option<&str>) -> Vec<String> {
+    converter
+        .names()
+        .flat_map(|set| units_of(converter, set, value))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(words: &[&str]) -> Vec<String> {
+        let converter =
+            Converter::new().with_str("distance", "km -> m: x * 1000\nm -> km: x / 1000", None);
+        let words = words
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>();
+        candidates(&converter, &words)
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(complete(&["dis"]), ["distance"]);
+        assert_eq!(complete(&["5"]), ["5km", "5m"]);
+        assert_eq!(complete(&["distance", "2k"]), ["2km"]);
+        assert_eq!(complete(&["distance", "2km", ""]), ["km", "m"]);
+        assert_eq!(complete(&["lint", ""]), ["distance"]);
+        assert_eq!(complete(&["completions", "z"]), ["zsh"]);
+        assert_eq!(complete(&["list", ""]), Vec::<String>::new());
+        assert_eq!(complete(&["lint", "distance", ""]), Vec::<String>::new());
+        assert_eq!(complete(&["completions", "zsh", ""]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_files() {
+        let dir = std::env::temp_dir().join(format!("manada-completion-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sets")).unwrap();
+        std::fs::write(dir.join("distance"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let prefix = format!("{}/", dir.display());
+        assert_eq!(
+            complete(&["fmt", &prefix]),
+            [format!("{prefix}distance"), format!("{prefix}sets/")]
+        );
+        assert_eq!(
+            complete(&["fmt", "a", &format!("{prefix}.")]),
+            [format!("{prefix}.hidden")]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,42 +1,391 @@
-use derive_more::From;
-use manada::NoFilePathError;
+use crate::{NoFilePathError, read_file};
+use derive_more::{Display, From};
 use serde::Deserialize;
-use std::{collections::{HashMap, HashSet}, fs::read_to_string, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 use toml::de::Error;
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     aliases: Option<HashMap<String, HashSet<String>>>,
+    /// Match units and aliases ignoring their case and Unicode normalization
+    case_insensitive: Option<bool>,
+    names: Option<HashMap<String, DisplayNames>>,
+    /// The number of days after which exchange rates are outdated
+    max_rate_age: Option<u32>,
+    #[serde(skip)]
+    index: AliasIndex,
+}
+
+/// The names used to display a unit, optionally translated
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DisplayNames {
+    singular: Option<String>,
+    plural: Option<String>,
+    #[serde(default)]
+    locales: HashMap<String, DisplayNames>,
+}
+
+impl DisplayNames {
+    /// The plural falls back to the singular
+    fn get(&self, plural: bool) -> Option<&str> {
+        if plural {
+            self.plural.as_deref().or(self.singular.as_deref())
+        } else {
+            self.singular.as_deref()
+        }
+    }
+}
+
+/// Maps every alias to the name of its unit
+#[derive(Debug, Clone, Default)]
+pub struct AliasIndex {
+    units: HashMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Display)]
+pub enum AliasError {
+    #[display("The alias {alias} is used for both {first} and {second}")]
+    Collision {
+        alias: String,
+        first: String,
+        second: String,
+    },
+    #[display("The alias {alias} of {unit} is also the name of another unit")]
+    Shadows { alias: String, unit: String },
+    #[display("{first} and {second} can't be told apart when ignoring the case")]
+    FoldCollision { first: String, second: String },
+}
+
+impl std::error::Error for AliasError {}
+
+/// Case folds and normalizes a name for case-insensitive matching
+pub fn fold(name: &str) -> String {
+    caseless::default_case_fold_str(&name.nfc().collect::<String>())
+        .nfc()
+        .collect()
+}
+
+impl AliasIndex {
+    pub fn insert(&mut self, alias: &str, unit: &str) -> Result<(), AliasError> {
+        match self.units.get(alias) {
+            Some(existing) if existing != unit => Err(AliasError::Collision {
+                alias: alias.to_string(),
+                first: existing.clone(),
+                second: unit.to_string(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                self.units.insert(alias.to_string(), unit.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get(&self, alias: &str) -> Option<&str> {
+        self.units.get(alias).map(String::as_str)
+    }
+
+    /// An index of the case folded units and aliases, errors if two different units are the same
+    /// after folding
+    pub fn case_folded<'a>(
+        &self,
+        units: impl Iterator<Item = &'a str>,
+    ) -> Result<AliasIndex, AliasError> {
+        let mut folded = AliasIndex::default();
+        let names = units.map(|unit| (unit, unit)).chain(
+            self.units
+                .iter()
+                .map(|(alias, unit)| (alias.as_str(), unit.as_str())),
+        );
+        for (name, unit) in names {
+            folded.insert(&fold(name), unit).map_err(|err| match err {
+                AliasError::Collision { first, second, .. } => {
+                    AliasError::FoldCollision { first, second }
+                }
+                err => err,
+            })?;
+        }
+        Ok(folded)
+    }
+
+    /// Errors if an alias is the name of a different unit
+    pub fn check_shadowing<'a>(
+        &self,
+        mut units: impl Iterator<Item = &'a str>,
+    ) -> Result<(), AliasError> {
+        units.try_for_each(|name| match self.units.get(name) {
+            Some(unit) if unit != name => Err(AliasError::Shadows {
+                alias: name.to_string(),
+                unit: unit.clone(),
+            }),
+            _ => Ok(()),
+        })
+    }
 }
 
 impl Config {
+    /// Reads and merges the config files of a unit set from all search directories
     pub fn try_new(unit_set: &str) -> Result<Self, NewConfigError> {
-        let path = manada::file_path(format!("{unit_set}.toml"))?;
-        let file_content = match read_to_string(&path) {
+        let paths = crate::file_paths(format!("{unit_set}.toml"));
+        if paths.is_empty() {
+            return Err(NewConfigError::NoConfig);
+        }
+
+        let mut config = Self {
+            aliases: None,
+            case_insensitive: None,
+            names: None,
+            max_rate_age: None,
+            index: AliasIndex::default(),
+        };
+        for path in paths.into_iter().rev() {
+            config.merge(Self::from_file(path)?);
+        }
+        config.indexed()
+    }
+
+    /// Reads a single config file, errors with [`NewConfigError::NoConfig`] if it doesn't exist
+    pub fn from_path(path: PathBuf) -> Result<Self, NewConfigError> {
+        if !path.is_file() {
+            return Err(NewConfigError::NoConfig);
+        }
+        Self::from_file(path)?.indexed()
+    }
+
+    /// Parses the content of a config file, `path` is only used for errors
+    pub fn from_content(path: PathBuf, content: &str) -> Result<Self, NewConfigError> {
+        Self::parse(path, content)?.indexed()
+    }
+
+    fn from_file(path: PathBuf) -> Result<Self, NewConfigError> {
+        let file_content = match read_file(&path) {
             Ok(f) => f,
             Err(error) => return Err(NewConfigError::FileRead { path, error }),
         };
-        toml::from_str(&file_content).map_err(|error| NewConfigError::ParseError { path, error })
+        Self::parse(path, &file_content)
     }
 
-    pub fn get_full_unit(self, aliased: &str) -> Option<String> {
-        let aliases = self.aliases?;
+    fn parse(path: PathBuf, content: &str) -> Result<Self, NewConfigError> {
+        toml::from_str(content).map_err(|error| NewConfigError::ParseError { path, error })
+    }
 
-        aliases.into_iter().find_map(|(key, value)| {
-            ((key == aliased) || (value.into_iter().any(|s| s == aliased))).then_some(key)
-        })
+    fn indexed(mut self) -> Result<Self, NewConfigError> {
+        for (unit, aliases) in self.aliases.iter().flatten() {
+            for alias in aliases {
+                self.index.insert(alias, unit)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Merges a config with a higher priority, whose aliases of a unit replace the lower ones.
+    /// Its aliases are taken away from the other units of the lower configs.
+    fn merge(&mut self, other: Self) {
+        self.case_insensitive = other.case_insensitive.or(self.case_insensitive);
+        self.max_rate_age = other.max_rate_age.or(self.max_rate_age);
+        if let Some(other_names) = other.names {
+            self.names
+                .get_or_insert_with(HashMap::new)
+                .extend(other_names);
+        }
+        let Some(other_aliases) = other.aliases else {
+            return;
+        };
+        let aliases = self.aliases.get_or_insert_with(HashMap::new);
+        for unit_aliases in aliases.values_mut() {
+            unit_aliases.retain(|alias| !other_aliases.values().any(|other| other.contains(alias)));
+        }
+        aliases.extend(other_aliases);
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        self.aliases
+            .iter()
+            .flat_map(|aliases| aliases.values())
+            .flatten()
+            .map(String::as_str)
+    }
+
+    pub fn aliases_of(&self, unit: &str) -> Vec<&str> {
+        let mut aliases = self
+            .aliases
+            .as_ref()
+            .and_then(|aliases| aliases.get(unit))
+            .map_or_else(Vec::new, |aliases| {
+                aliases.iter().map(String::as_str).collect()
+            });
+        aliases.sort_unstable();
+        aliases
+    }
+
+    /// The display name of a unit in the first of `locales` which has one, falling back to the
+    /// untranslated name
+    pub fn display_name(&self, unit: &str, locales: &[&str], plural: bool) -> Option<&str> {
+        let names = self.names.as_ref()?.get(unit)?;
+        locales
+            .iter()
+            .find_map(|&locale| names.locales.get(locale)?.get(plural))
+            .or_else(|| names.get(plural))
+    }
+
+    pub const DEFAULT_MAX_RATE_AGE: u32 = 7;
+
+    pub fn max_rate_age(&self) -> u32 {
+        self.max_rate_age.unwrap_or(Self::DEFAULT_MAX_RATE_AGE)
+    }
+
+    pub fn case_insensitive(&self) -> bool {
+        self.case_insensitive.unwrap_or(false)
+    }
+
+    pub fn index(&self) -> &AliasIndex {
+        &self.index
     }
 }
 
-#[derive(From)]
+#[derive(Debug, Display, From)]
 pub enum NewConfigError {
     #[from(NoFilePathError)]
+    #[display("There is no config")]
     NoConfig,
     #[from]
+    #[display("Can't read file {} ({})", path.display(), error.kind())]
     FileRead {
         path: PathBuf,
         error: std::io::Error,
     },
     #[from]
+    #[display("Can't parse {}: {error}", path.display())]
     ParseError { path: PathBuf, error: Error },
+    #[from]
+    #[display("{_0}")]
+    Alias(AliasError),
+}
+
+impl std::error::Error for NewConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_index() {
+        let mut index = AliasIndex::default();
+        index.insert("meter", "m").unwrap();
+        index.insert("meter", "m").unwrap();
+        assert_eq!(index.get("meter"), Some("m"));
+        assert_eq!(index.get("m"), None);
+
+        assert_eq!(
+            index.insert("meter", "km"),
+            Err(AliasError::Collision {
+                alias: String::from("meter"),
+                first: String::from("m"),
+                second: String::from("km"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Kilometer"), fold("kilometer"));
+        assert_eq!(fold("FUSS"), fold("fuß"));
+        assert_eq!(fold("Fu\u{0073}\u{0073}"), fold("fuss"));
+        assert_eq!(fold("Fu\u{00df}e"), fold("fu\u{00df}e"));
+        assert_eq!(fold("fu\u{0308}r"), fold("für"));
+        assert_ne!(fold("m"), fold("mm"));
+    }
+
+    #[test]
+    fn test_case_folded() {
+        let mut index = AliasIndex::default();
+        index.insert("Meter", "m").unwrap();
+        index.insert("meter", "m").unwrap();
+        let folded = index.case_folded(["m", "km"].into_iter()).unwrap();
+        assert_eq!(folded.get(&fold("METER")), Some("m"));
+        assert_eq!(folded.get(&fold("KM")), Some("km"));
+
+        assert_eq!(
+            AliasIndex::default()
+                .case_folded(["mb", "Mb"].into_iter())
+                .err(),
+            Some(AliasError::FoldCollision {
+                first: String::from("mb"),
+                second: String::from("Mb"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_name() {
+        let config: Config = toml::from_str(
+            r#"
+            [names.mi]
+            singular = "mile"
+            plural = "miles"
+            locales.de = { singular = "Meile", plural = "Meilen" }
+
+            [names.ft]
+            singular = "foot"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.display_name("mi", &[], false), Some("mile"));
+        assert_eq!(config.display_name("mi", &[], true), Some("miles"));
+        assert_eq!(
+            config.display_name("mi", &["de-AT", "de"], true),
+            Some("Meilen")
+        );
+        assert_eq!(config.display_name("mi", &["fr"], true), Some("miles"));
+        assert_eq!(config.display_name("ft", &[], true), Some("foot"));
+        assert_eq!(config.display_name("km", &[], true), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut config: Config = toml::from_str(
+            r#"
+            aliases.mi = ["mile", "miles"]
+            aliases.ft = ["foot", "feet"]
+            aliases.m = ["meter"]
+            "#,
+        )
+        .unwrap();
+        config.merge(
+            toml::from_str(
+                r#"
+                aliases.mi = ["mile"]
+                aliases.nmi = ["miles"]
+                aliases.ft = []
+                "#,
+            )
+            .unwrap(),
+        );
+        let config = config.indexed().unwrap();
+
+        assert_eq!(config.aliases_of("mi"), ["mile"]);
+        assert_eq!(config.aliases_of("nmi"), ["miles"]);
+        assert!(config.aliases_of("ft").is_empty());
+        assert_eq!(config.aliases_of("m"), ["meter"]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let mut index = AliasIndex::default();
+        index.insert("km", "yard").unwrap();
+        index.insert("m", "m").unwrap();
+        assert!(index.check_shadowing(["m", "cm"].into_iter()).is_ok());
+        assert_eq!(
+            index.check_shadowing(["m", "km"].into_iter()),
+            Err(AliasError::Shadows {
+                alias: String::from("km"),
+                unit: String::from("yard"),
+            })
+        );
+    }
 }
@@ -0,0 +1,754 @@
+use crate::{
+    NoFilePathError, bundled,
+    config::{AliasError, AliasIndex, Config, NewConfigError, fold},
+    dimension::{DimensionError, Dimensions},
+    file_path,
+    input::{Quantity, QuantityParseError, parse_quantity},
+    parse::{ConversionError, ParseError, Parsed, Source, SourceError, read_sources},
+    rates::{Date, Rates, RatesError},
+    read_file, search_dirs,
+};
+use derive_more::Display;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashSet},
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// All unit sets found in the search directories or added from files and strings, their files are
+/// only read once they are needed
+#[derive(Default)]
+pub struct Converter {
+    sets: BTreeMap<String, SetEntry>,
+    /// Match units ignoring their case even if the unit set doesn't enable it
+    ignore_case: bool,
+}
+
+struct SetEntry {
+    /// Every file of this set, sorted by priority
+    paths: Vec<PathBuf>,
+    /// The compiled in conversions, which have a lower priority than all files
+    bundled: Option<&'static str>,
+    /// The files and everything they include, in the order they are applied
+    sources: OnceCell<Vec<Source>>,
+    config: ConfigSource,
+}
+
+impl SetEntry {
+    /// A set from the search directories, its files are added while discovering them
+    fn discovered() -> Self {
+        Self {
+            paths: Vec::new(),
+            bundled: None,
+            sources: OnceCell::new(),
+            config: ConfigSource::Search,
+        }
+    }
+}
+
+/// Where the config of a unit set comes from
+enum ConfigSource {
+    /// The config files named after the set in all search directories
+    Search,
+    /// A single config file, which doesn't have to exist
+    File(PathBuf),
+    Content(String),
+    /// The compiled in config named after the set
+    Bundled,
+    None,
+}
+
+/// A loaded unit set, which is independent of the [`Converter`] it was loaded from
+pub struct UnitSet {
+    pub(crate) name: String,
+    pub(crate) parsed: Parsed,
+    pub(crate) config: Option<Config>,
+    /// The aliases of both the conversions file and the config
+    aliases: AliasIndex,
+    /// The case folded units and aliases, if the set is matched case-insensitively
+    folded: Option<AliasIndex>,
+    /// The unit sets declared with `@import`
+    imports: Vec<UnitSet>,
+}
+
+#[derive(Debug, Display)]
+pub enum LoadError {
+    #[display("{_0}")]
+    NotFound(NoFilePathError),
+    #[display("Can't read file {} ({})", path.display(), error.kind())]
+    FileRead {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    #[display("{error} of {}", source.path.display())]
+    Parse { source: Source, error: ParseError },
+    #[display("{_0}")]
+    Config(NewConfigError),
+    #[display("Invalid aliases: {_0}")]
+    Alias(AliasError),
+    #[display("Can't import {name}: {error}")]
+    Import { name: String, error: Box<LoadError> },
+    #[display("Importing {} results in a cycle", _0.join(" -> "))]
+    ImportCycle(Vec<String>),
+    #[display("Can't use the rates of {}: {error}", path.display())]
+    Rates { path: PathBuf, error: RatesError },
+}
+
+impl std::error::Error for LoadError {}
+
+impl Converter {
+    /// A converter without any unit sets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn discover() -> Self {
+        let mut sets = BTreeMap::new();
+        // The directories are sorted by priority, so the first file of a set wins
+        for dir in search_dirs() {
+            let Ok(entries) = read_dir(&dir) else {
+                continue;
+            };
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                let Some(name) = set_name(&path) else {
+                    continue;
+                };
+                sets.entry(name)
+                    .or_insert_with(SetEntry::discovered)
+                    .paths
+                    .push(path);
+            }
+        }
+        for name in bundled::sets() {
+            sets.entry(name.to_string())
+                .or_insert_with(SetEntry::discovered)
+                .bundled = bundled::file(name);
+        }
+
+        Self {
+            sets,
+            ignore_case: false,
+        }
+    }
+
+    /// A converter with only the compiled in unit sets and configs, ignoring the search
+    /// directories
+    pub fn bundled() -> Self {
+        let sets = bundled::sets()
+            .map(|name| {
+                let entry = SetEntry {
+                    bundled: bundled::file(name),
+                    config: ConfigSource::Bundled,
+                    ..SetEntry::discovered()
+                };
+                (name.to_string(), entry)
+            })
+            .collect();
+
+        Self {
+            sets,
+            ignore_case: false,
+        }
+    }
+
+    pub fn ignore_case(self, ignore_case: bool) -> Self {
+        Self {
+            ignore_case,
+            ..self
+        }
+    }
+
+    /// Adds a conversions file as a unit set named after the file, replacing a set with the same
+    /// name. A `<file>.toml` next to it is used as its config.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let mut config = path.clone().into_os_string();
+        config.push(".toml");
+
+        self.sets.insert(
+            name,
+            SetEntry {
+                paths: vec![path],
+                bundled: None,
+                sources: OnceCell::new(),
+                config: ConfigSource::File(config.into()),
+            },
+        );
+        self
+    }
+
+    /// Adds a unit set from the content of a conversions file and optionally of its config,
+    /// replacing a set with the same name. `@include` directives are ignored.
+    pub fn with_str(
+        mut self,
+        name: impl Into<String>,
+        conversions: impl Into<String>,
+        config: Option<String>,
+    ) -> Self {
+        let name = name.into();
+        let source = Source {
+            path: PathBuf::from(&name),
+            content: conversions.into(),
+        };
+
+        self.sets.insert(
+            name,
+            SetEntry {
+                paths: Vec::new(),
+                bundled: None,
+                sources: OnceCell::from(vec![source]),
+                config: config.map_or(ConfigSource::None, ConfigSource::Content),
+            },
+        );
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sets.keys().map(String::as_str)
+    }
+
+    /// Whether a unit set has compiled in conversions
+    pub fn is_bundled(&self, name: &str) -> bool {
+        self.sets
+            .get(name)
+            .is_some_and(|entry| entry.bundled.is_some())
+    }
+
+    /// Every unit set with all files it was found in, sorted by priority
+    pub fn locations(&self) -> impl Iterator<Item = (&str, &[PathBuf])> {
+        self.sets
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry.paths.as_slice()))
+    }
+
+    pub fn load(&self, name: &str) -> Result<UnitSet, LoadError> {
+        self.load_importing(name, &mut Vec::new())
+    }
+
+    /// Loads a unit set and the sets it imports, `importing` are the sets which are currently
+    /// being loaded
+    fn load_importing(
+        &self,
+        name: &str,
+        importing: &mut Vec<String>,
+    ) -> Result<UnitSet, LoadError> {
+        let (name, parsed) = self.parse(name)?;
+
+        let config = match &self.sets[name].config {
+            ConfigSource::Search => Config::try_new(name),
+            ConfigSource::File(path) => Config::from_path(path.clone()),
+            ConfigSource::Content(content) => {
+                Config::from_content(PathBuf::from(format!("{name}.toml")), content)
+            }
+            ConfigSource::Bundled => {
+                let file_name = format!("{name}.toml");
+                match bundled::file(&file_name) {
+                    Some(content) => Config::from_content(bundled::path(&file_name), content),
+                    None => Err(NewConfigError::NoConfig),
+                }
+            }
+            ConfigSource::None => Err(NewConfigError::NoConfig),
+        };
+        let config = match config {
+            Ok(config) => Some(config),
+            Err(NewConfigError::NoConfig) => None,
+            Err(err) => return Err(LoadError::Config(err)),
+        };
+
+        let mut aliases = config
+            .as_ref()
+            .map_or_else(AliasIndex::default, |config| config.index().clone());
+        for (alias, unit) in parsed.aliases() {
+            aliases.insert(alias, unit).map_err(LoadError::Alias)?;
+        }
+        aliases
+            .check_shadowing(parsed.units())
+            .map_err(LoadError::Alias)?;
+
+        let case_insensitive =
+            self.ignore_case || config.as_ref().is_some_and(Config::case_insensitive);
+        let folded = case_insensitive
+            .then(|| aliases.case_folded(parsed.units()))
+            .transpose()
+            .map_err(LoadError::Alias)?;
+
+        importing.push(name.to_string());
+        let mut imports = Vec::new();
+        for import in parsed.imports() {
+            if importing.iter().any(|name| name == import) {
+                let mut cycle = importing.clone();
+                cycle.push(import.to_string());
+                return Err(LoadError::ImportCycle(cycle));
+            }
+            let set = self
+                .load_importing(import, importing)
+                .map_err(|error| match error {
+                    LoadError::ImportCycle(cycle) => LoadError::ImportCycle(cycle),
+                    error => LoadError::Import {
+                        name: import.to_string(),
+                        error: Box::new(error),
+                    },
+                })?;
+            imports.push(set);
+        }
+        importing.pop();
+
+        Ok(UnitSet {
+            name: name.to_string(),
+            parsed,
+            config,
+            aliases,
+            folded,
+            imports,
+        })
+    }
+
+    /// Converts between unit expressions like `km/h` and `m/s`, whose units can come from all unit
+    /// sets. Sets which can't be loaded are skipped.
+    pub fn convert_compound(
+        &self,
+        value: Decimal,
+        start: &str,
+        end: &str,
+    ) -> Result<Decimal, DimensionError> {
+        Dimensions::new(&self.load_all()).convert(value, start, end)
+    }
+
+    /// The sets whose units occur in the unit expressions `start` and `end`
+    pub fn sets_of(&self, start: &str, end: &str) -> Vec<UnitSet> {
+        let sets = self.load_all();
+        let dimensions = Dimensions::new(&sets);
+        let names = [start, end]
+            .into_iter()
+            .filter_map(|expression| dimensions.sets_of(expression).ok())
+            .flatten()
+            .map(|set| set.name.clone())
+            .collect::<HashSet<_>>();
+        sets.into_iter()
+            .filter(|set| names.contains(&set.name))
+            .collect()
+    }
+
+    /// Every set which can be loaded
+    fn load_all(&self) -> Vec<UnitSet> {
+        self.names()
+            .filter_map(|name| self.load(name).ok())
+            .collect()
+    }
+
+    /// Parses the conversions files of a unit set without its config
+    pub fn parse(&self, name: &str) -> Result<(&str, Parsed), LoadError> {
+        let Some((name, entry)) = self.sets.get_key_value(name) else {
+            // Hidden files and directories aren't unit sets, reading them explains why
+            let path = file_path(name).map_err(LoadError::NotFound)?;
+            let error = read_to_string(&path)
+                .err()
+                .unwrap_or_else(|| std::io::ErrorKind::InvalidInput.into());
+            return Err(LoadError::FileRead { path, error });
+        };
+
+        let sources = match entry.sources.get() {
+            Some(sources) => sources,
+            None => {
+                let mut sources = entry
+                    .bundled
+                    .map(|content| Source {
+                        path: bundled::path(name),
+                        content: content.to_string(),
+                    })
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                // Files with a higher priority are applied last, so they can override conversions
+                for path in entry.paths.iter().rev() {
+                    sources.extend(read_sources(path)?);
+                }
+                entry.sources.get_or_init(|| sources)
+            }
+        };
+
+        let parse_error = |source: &Source, error| LoadError::Parse {
+            source: source.clone(),
+            error,
+        };
+        let (base, layers) = sources
+            .split_first()
+            .expect("every set has at least one file");
+        let mut parsed =
+            Parsed::try_new(&base.content).map_err(|error| parse_error(base, error))?;
+        for source in layers {
+            parsed
+                .try_extend(&source.content)
+                .map_err(|error| parse_error(source, error))?;
+        }
+        if let Some(file_name) = parsed.rates() {
+            let rates = read_rates(file_name)?;
+            parsed.add_rates(&rates);
+        }
+
+        Ok((name, parsed))
+    }
+}
+
+/// Reads a rates file from the search directories, unless its path is absolute
+fn read_rates(file_name: &str) -> Result<Rates, LoadError> {
+    let path = if Path::new(file_name).is_absolute() {
+        PathBuf::from(file_name)
+    } else {
+        file_path(file_name).map_err(|error| LoadError::Rates {
+            path: PathBuf::from(file_name),
+            error: error.into(),
+        })?
+    };
+    let content = match read_file(&path) {
+        Ok(content) => content,
+        Err(error) => return Err(LoadError::FileRead { path, error }),
+    };
+    Rates::parse(&path, &content).map_err(|error| LoadError::Rates { path, error })
+}
+
+impl From<SourceError> for LoadError {
+    fn from(err: SourceError) -> Self {
+        match err {
+            SourceError::FileRead { path, error } => Self::FileRead { path, error },
+            SourceError::Parse { source, error } => Self::Parse { source, error },
+        }
+    }
+}
+
+impl UnitSet {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The units and conversions of the set, without the ones of the imported sets
+    pub fn parsed(&self) -> &Parsed {
+        &self.parsed
+    }
+
+    /// The merged config files of the set, if there are any
+    pub fn config(&self) -> Option<&Config> {
+        self.config.as_ref()
+    }
+
+    /// Every name a unit of this set can be referred to by
+    pub fn unit_names(&self) -> impl Iterator<Item = &str> {
+        self.parsed
+            .units()
+            .chain(self.parsed.aliases().map(|(alias, _)| alias))
+            .chain(self.config.iter().flat_map(Config::aliases))
+    }
+
+    /// The names of all units, sorted
+    pub fn units(&self) -> Vec<&str> {
+        let mut units = self.parsed.units().collect::<Vec<_>>();
+        units.sort_unstable();
+        units
+    }
+
+    /// The aliases of a unit from both the conversions file and the config
+    pub fn aliases_of(&self, unit: &str) -> Vec<&str> {
+        let declared = self.parsed.metadata(unit).map(|metadata| &metadata.aliases);
+        let mut aliases = declared
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(
+                self.config
+                    .iter()
+                    .flat_map(|config| config.aliases_of(unit)),
+            )
+            .collect::<Vec<_>>();
+        aliases.sort_unstable();
+        aliases.dedup();
+        aliases
+    }
+
+    pub fn quantity(&self, raw: &str) -> Result<Quantity, QuantityParseError> {
+        parse_quantity(raw, self.unit_names())
+    }
+
+    /// Resolves an alias to the name of its node, unknown names are returned as they are
+    pub fn resolve(&self, unit: &str) -> String {
+        if self.parsed.get_node_by_name(unit).is_some() {
+            return unit.to_string();
+        }
+
+        self.aliases
+            .get(unit)
+            .or_else(|| self.folded.as_ref()?.get(&fold(unit)))
+            .unwrap_or(unit)
+            .to_string()
+    }
+
+    /// Converts `value` from the unit `start` to `end`, both can also be aliases
+    pub fn convert(
+        &self,
+        value: Decimal,
+        start: &str,
+        end: &str,
+    ) -> Result<Decimal, ConversionError> {
+        self.parsed
+            .convert(&self.resolve(start), &self.resolve(end), value)
+    }
+
+    /// Converts `value` with an `uncertainty` from the unit `start` to `end`, returning the
+    /// converted value and uncertainty
+    pub fn convert_uncertain(
+        &self,
+        value: Decimal,
+        uncertainty: Decimal,
+        start: &str,
+        end: &str,
+    ) -> Result<(Decimal, Decimal), ConversionError> {
+        self.parsed
+            .convert_uncertain(&self.resolve(start), &self.resolve(end), value, uncertainty)
+    }
+
+    /// Converts every value from `low` to `high` of the unit `start` to `end`, returning the lowest
+    /// and highest result
+    pub fn convert_range(
+        &self,
+        low: Decimal,
+        high: Decimal,
+        start: &str,
+        end: &str,
+    ) -> Result<(Decimal, Decimal), ConversionError> {
+        self.parsed
+            .convert_range(&self.resolve(start), &self.resolve(end), low, high)
+    }
+
+    pub fn contains(&self, unit: &str) -> bool {
+        self.parsed.get_node_by_name(&self.resolve(unit)).is_some()
+    }
+
+    /// The age of the exchange rates in days, if they are older than the config allows
+    pub fn outdated_rates(&self, today: Date) -> Option<i64> {
+        let age = self.parsed.rates_date()?.days_until(today);
+        let max_age = self
+            .config
+            .as_ref()
+            .map_or(Config::DEFAULT_MAX_RATE_AGE, Config::max_rate_age);
+        (age > i64::from(max_age)).then_some(age)
+    }
+
+    /// The unit sets imported by this set
+    pub fn imports(&self) -> &[UnitSet] {
+        &self.imports
+    }
+
+    /// Converts between unit expressions of this set and the sets it imports, e.g. `mbit/s` into
+    /// `mb/min` in a set importing `data` and `time`
+    pub fn convert_compound(
+        &self,
+        value: Decimal,
+        start: &str,
+        end: &str,
+    ) -> Result<Decimal, DimensionError> {
+        Dimensions::new(self.with_imports()).convert(value, start, end)
+    }
+
+    /// The sets out of this set and its imports whose units occur in the unit expressions `start`
+    /// and `end`
+    pub fn sets_of(&self, start: &str, end: &str) -> Vec<&UnitSet> {
+        let dimensions = Dimensions::new(self.with_imports());
+        [start, end]
+            .into_iter()
+            .filter_map(|expression| dimensions.sets_of(expression).ok())
+            .flatten()
+            .unique_by(|set| &set.name)
+            .collect()
+    }
+
+    /// This set and every set it imports directly or indirectly
+    fn with_imports(&self) -> Vec<&UnitSet> {
+        let mut sets = vec![self];
+        let mut i = 0;
+        // Sets imported by multiple sets are only used once
+        while let Some(set) = sets.get(i) {
+            for import in &set.imports {
+                if !sets.iter().any(|set| set.name == import.name) {
+                    sets.push(import);
+                }
+            }
+            i += 1;
+        }
+        sets
+    }
+}
+
+fn set_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let is_toml = path.extension().is_some_and(|ext| ext == "toml");
+    let is_set = path.is_file() && !name.starts_with('.') && !is_toml;
+    is_set.then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const DISTANCE: &str =
+        "unit km aliases: kilometre\nkm -> m: x * 1000\nm -> km: x / 1000\nunit ft";
+
+    #[test]
+    fn test_with_str() {
+        let config = String::from("[aliases]\nm = [\"metre\"]");
+        let converter = Converter::new().with_str("distance", DISTANCE, Some(config));
+        let distance = converter.load("distance").ok().unwrap();
+        // Loaded sets don't borrow the converter, so they can be moved to other threads
+        drop(converter);
+        let distance = std::thread::spawn(move || distance).join().ok().unwrap();
+
+        assert_eq!(distance.units(), vec!["ft", "km", "m"]);
+        assert_eq!(distance.resolve("metre"), "m");
+        assert_eq!(
+            distance.convert(dec!(2), "kilometre", "metre"),
+            Ok(dec!(2000))
+        );
+        assert_eq!(
+            distance.convert(dec!(2), "km", "ft"),
+            Err(ConversionError::NoPathFound {
+                start: String::from("km"),
+                end: String::from("ft")
+            })
+        );
+        assert_eq!(
+            distance.convert(dec!(2), "mile", "m"),
+            Err(ConversionError::StartDoesntExist(String::from("mile")))
+        );
+        assert_eq!(
+            distance.convert(dec!(2), "m", "mile"),
+            Err(ConversionError::EndDoesntExist(String::from("mile")))
+        );
+    }
+
+    #[test]
+    fn test_with_file() {
+        let dir = std::env::temp_dir().join(format!("manada-converter-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("distance"), DISTANCE).unwrap();
+        std::fs::write(dir.join("distance.toml"), "case_insensitive = true").unwrap();
+
+        let converter = Converter::new().with_file(dir.join("distance"));
+        let distance = converter.load("distance").ok().unwrap();
+        assert_eq!(distance.convert(dec!(3), "KM", "m"), Ok(dec!(3000)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rates() {
+        let dir = std::env::temp_dir().join(format!("manada-rates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rates = dir.join("eurofxref.csv");
+        std::fs::write(&rates, "Date, USD, GBP, \n16 October 2026, 1.25, 0.8, \n").unwrap();
+
+        let conversions = format!("@rates {}\nunit eur aliases: euro", rates.display());
+        let config = String::from("max_rate_age = 3");
+        let converter = Converter::new()
+            .with_str("currency", conversions, Some(config))
+            .with_str("missing", "@rates missing.csv", None);
+        let currency = converter.load("currency").unwrap();
+        assert_eq!(currency.units(), vec!["eur", "gbp", "usd"]);
+        assert_eq!(currency.convert(dec!(10), "euro", "usd"), Ok(dec!(12.5)));
+        assert_eq!(currency.convert(dec!(10), "usd", "gbp"), Ok(dec!(6.4)));
+
+        let date = Date::parse("2026-10-16").unwrap();
+        assert_eq!(currency.parsed.rates_date(), Some(date));
+        assert_eq!(currency.outdated_rates(date), None);
+        assert_eq!(
+            currency.outdated_rates(Date::parse("2026-10-20").unwrap()),
+            Some(4)
+        );
+        assert!(matches!(
+            converter.load("missing"),
+            Err(LoadError::Rates {
+                error: RatesError::NotFound(_),
+                ..
+            })
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "bundled")]
+    #[test]
+    fn test_bundled() {
+        let converter = Converter::bundled();
+        assert!(converter.is_bundled("distance"));
+        assert!(converter.locations().all(|(_, paths)| paths.is_empty()));
+        let temperature = converter.load("temperature").ok().unwrap();
+        assert_eq!(temperature.convert(dec!(100), "c", "f"), Ok(dec!(212)));
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let converter = Converter::new()
+            .with_str("broken", "km -> m x * 1000", None)
+            .with_str(
+                "aliases",
+                DISTANCE,
+                Some(String::from("[aliases]\nkm = [\"m\"]")),
+            );
+
+        assert_eq!(
+            converter.load("broken").err().unwrap().to_string(),
+            "Missing arrow \": \" between second unit and conversion in line 1 of broken"
+        );
+        assert_eq!(
+            converter.load("aliases").err().unwrap().to_string(),
+            "Invalid aliases: The alias m of km is also the name of another unit"
+        );
+    }
+
+    #[test]
+    fn test_imports() {
+        let converter = Converter::new()
+            .with_str("distance", DISTANCE, None)
+            .with_str("time", "@dimension time\nh -> s: x * 3600\ns -> h: x / 3600", None)
+            .with_str(
+                "speed",
+                "@derived mps = m / s\n@import distance\n@import time\nkmh -> mps: x / 3.6\nmps -> kmh: x * 3.6",
+                None,
+            )
+            .with_str("a", "@import b\nunit a", None)
+            .with_str("b", "@import a\nunit b", None)
+            .with_str("missing", "@import nothing\nunit m", None);
+
+        let speed = converter.load("speed").unwrap();
+        assert_eq!(
+            speed
+                .imports()
+                .iter()
+                .map(|set| &set.name)
+                .collect::<Vec<_>>(),
+            vec!["distance", "time"]
+        );
+        assert_eq!(speed.convert_compound(dec!(36), "kmh", "m/s"), Ok(dec!(10)));
+        assert_eq!(speed.convert_compound(dec!(1), "km/h", "kmh"), Ok(dec!(1)));
+        let names = |sets: Vec<&UnitSet>| -> Vec<String> {
+            sets.iter().map(|set| set.name.clone()).collect()
+        };
+        assert_eq!(names(speed.sets_of("km/h", "m/s")), ["distance", "time"]);
+        assert_eq!(names(speed.sets_of("kmh", "m")), ["distance", "time"]);
+        let sets = converter.sets_of("km", "h");
+        assert_eq!(names(sets.iter().collect()), ["distance", "time"]);
+
+        assert_eq!(
+            converter.load("a").err().unwrap().to_string(),
+            "Importing a -> b -> a results in a cycle"
+        );
+        assert!(matches!(
+            converter.load("missing"),
+            Err(LoadError::Import { name, .. }) if name == "nothing"
+        ));
+    }
+}
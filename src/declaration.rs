@@ -0,0 +1,181 @@
+use derive_more::Display;
+use serde::Serialize;
+
+/// Everything a `unit` declaration in a conversions file can describe
+#[derive(Debug, PartialEq, Eq, Default, Clone, Serialize)]
+pub struct UnitMetadata {
+    pub long_name: Option<String>,
+    pub symbol: Option<String>,
+    pub description: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Display)]
+pub enum DeclarationError {
+    #[display("Missing unit name in the declaration")]
+    MissingName,
+    #[display("Missing closing quote in the declaration")]
+    UnclosedQuote,
+    #[display("Missing value after {_0}: in the declaration")]
+    MissingValue(&'static str),
+    #[display("Unexpected \"{_0}\" in the declaration")]
+    Unexpected(String),
+}
+
+impl std::error::Error for DeclarationError {}
+
+impl UnitMetadata {
+    /// Adds the metadata of a later declaration of the same unit
+    pub fn merge(&mut self, other: Self) {
+        self.long_name = other.long_name.or(self.long_name.take());
+        self.symbol = other.symbol.or(self.symbol.take());
+        self.description = other.description.or(self.description.take());
+        for alias in other.aliases {
+            if !self.aliases.contains(&alias) {
+                self.aliases.push(alias);
+            }
+        }
+    }
+}
+
+/// Splits a quoted string from the start of `s`, returning its content and the rest
+fn quoted(s: &str) -> Result<(&str, &str), DeclarationError> {
+    let s = s.strip_prefix('"').ok_or(DeclarationError::UnclosedQuote)?;
+    let end = s.find('"').ok_or(DeclarationError::UnclosedQuote)?;
+    Ok((&s[..end], &s[end + 1..]))
+}
+
+/// Splits a word or a quoted string from the start of `s`, returning it and the rest
+fn word(s: &str) -> Result<(&str, &str), DeclarationError> {
+    if s.starts_with('"') {
+        return quoted(s);
+    }
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    Ok((&s[..end], &s[end..]))
+}
+
+/// Parses the part after `unit` of a declaration like
+/// `unit m "metre" symbol: m description: "SI base unit" aliases: meter, meters`
+///
+/// Everything besides the name is optional, the aliases have to come last.
+pub fn parse_declaration(declaration: &str) -> Result<(&str, UnitMetadata), DeclarationError> {
+    let (name, mut rest) = word(declaration.trim_start())?;
+    if name.is_empty() {
+        return Err(DeclarationError::MissingName);
+    }
+
+    let mut metadata = UnitMetadata::default();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok((name, metadata));
+        }
+
+        if rest.starts_with('"') {
+            let (long_name, after) = quoted(rest)?;
+            metadata.long_name = Some(long_name.to_string());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("symbol:") {
+            let (symbol, after) = word(after.trim_start())?;
+            if symbol.is_empty() {
+                return Err(DeclarationError::MissingValue("symbol"));
+            }
+            metadata.symbol = Some(symbol.to_string());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("description:") {
+            let after = after.trim_start();
+            if after.is_empty() {
+                return Err(DeclarationError::MissingValue("description"));
+            }
+            let (description, after) = quoted(after)?;
+            metadata.description = Some(description.to_string());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("aliases:") {
+            metadata.aliases = after
+                .split(',')
+                .map(str::trim)
+                .filter(|alias| !alias.is_empty())
+                .map(String::from)
+                .collect();
+            if metadata.aliases.is_empty() {
+                return Err(DeclarationError::MissingValue("aliases"));
+            }
+            return Ok((name, metadata));
+        } else {
+            let (unexpected, _) = word(rest)?;
+            return Err(DeclarationError::Unexpected(unexpected.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_declaration() {
+        assert_eq!(
+            parse_declaration(
+                r#"m "metre" symbol: m description: "The SI base unit" aliases: meter, meters"#
+            ),
+            Ok((
+                "m",
+                UnitMetadata {
+                    long_name: Some(String::from("metre")),
+                    symbol: Some(String::from("m")),
+                    description: Some(String::from("The SI base unit")),
+                    aliases: vec![String::from("meter"), String::from("meters")],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_partial_declaration() {
+        assert_eq!(parse_declaration("km"), Ok(("km", UnitMetadata::default())));
+        assert_eq!(
+            parse_declaration(r#"ft aliases: foot,feet , Fuß"#),
+            Ok((
+                "ft",
+                UnitMetadata {
+                    aliases: vec![
+                        String::from("foot"),
+                        String::from("feet"),
+                        String::from("Fuß")
+                    ],
+                    ..Default::default()
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_declaration(""), Err(DeclarationError::MissingName));
+        assert_eq!(
+            parse_declaration(r#"m "metre"#),
+            Err(DeclarationError::UnclosedQuote)
+        );
+        assert_eq!(
+            parse_declaration("m aliases:"),
+            Err(DeclarationError::MissingValue("aliases"))
+        );
+        assert_eq!(
+            parse_declaration("m plural: metres"),
+            Err(DeclarationError::Unexpected(String::from("plural:")))
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut metadata = parse_declaration(r#"m "metre" aliases: meter"#).unwrap().1;
+        metadata.merge(
+            parse_declaration(r#"m symbol: m aliases: meter, Meter"#)
+                .unwrap()
+                .1,
+        );
+        assert_eq!(metadata.long_name.as_deref(), Some("metre"));
+        assert_eq!(metadata.symbol.as_deref(), Some("m"));
+        assert_eq!(metadata.aliases, vec!["meter", "Meter"]);
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{ConversionError, Linearity, UnitSet};
+use derive_more::{Display, From};
+use itertools::Itertools;
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{self, Formatter},
+};
+
+/// How often derived units can refer to other derived units, deeper expansions are cycles
+const MAX_DEPTH: usize = 16;
+
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+/// The exponent of every base dimension of a unit, e.g. `length * time^-1` for a speed
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct Dimension(BTreeMap<String, i32>);
+
+#[derive(Debug, PartialEq, Eq, Display, From)]
+pub enum DimensionError {
+    #[display("Invalid unit expression \"{_0}\"")]
+    InvalidExpression(String),
+    #[display("There is no {_0} in any unit set")]
+    UnknownUnit(String),
+    #[display("{unit} is ambiguous, it is in {}", sets.join(" and "))]
+    Ambiguous { unit: String, sets: Vec<String> },
+    #[display("Can't convert {start} to {end}, their dimensions differ")]
+    Mismatch { start: Dimension, end: Dimension },
+    #[display("{_0} can't be part of a compound unit, as its conversions aren't proportional")]
+    NotProportional(String),
+    #[display("The derived units of {_0} form a cycle")]
+    Cycle(String),
+    #[from]
+    #[display("{_0}")]
+    Conversion(ConversionError),
+}
+
+impl Error for DimensionError {}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "a dimensionless unit");
+        }
+        let factors = self.0.iter().map(|(name, exponent)| match exponent {
+            1 => name.clone(),
+            exponent => format!("{name}^{exponent}"),
+        });
+        write!(f, "{}", factors.format(" * "))
+    }
+}
+
+/// Whether a unit is a product or quotient of units like `km/h`, `kg*m^2` or `m²`
+pub fn is_expression(unit: &str) -> bool {
+    unit.contains(['*', '·', '/', '^', '⁻']) || unit.contains(SUPERSCRIPT_DIGITS)
+}
+
+/// Splits a unit expression into its units and their exponents, e.g. `kg * m^2 / s²` into
+/// `kg`, `m` with 2 and `s` with -2. A division only applies to the unit directly after it, the
+/// unit `1` is skipped so `1/s` can be written.
+pub fn parse_unit_expression(expression: &str) -> Result<Vec<(&str, i32)>, DimensionError> {
+    let invalid = || DimensionError::InvalidExpression(expression.to_string());
+
+    let mut factors = Vec::new();
+    let mut sign = 1;
+    let mut rest = expression.trim();
+    loop {
+        let end = rest.find(['*', '·', '/']).unwrap_or(rest.len());
+        let (name, exponent) = parse_factor(rest[..end].trim()).ok_or_else(invalid)?;
+        if name != "1" {
+            factors.push((name, sign * exponent));
+        }
+
+        let Some(operator) = rest[end..].chars().next() else {
+            return Ok(factors);
+        };
+        sign = if operator == '/' { -1 } else { 1 };
+        rest = &rest[end + operator.len_utf8()..];
+    }
+}
+
+/// A single unit with an optional exponent like `m^2`, `m^-1` or `m²`
+fn parse_factor(factor: &str) -> Option<(&str, i32)> {
+    let (name, exponent) = match factor.split_once('^') {
+        Some((name, exponent)) => (name.trim_end(), exponent.trim_start().parse().ok()?),
+        None => {
+            let name = factor.trim_end_matches(|c| SUPERSCRIPT_DIGITS.contains(&c) || c == '⁻');
+            let superscript = &factor[name.len()..];
+            let exponent = if superscript.is_empty() {
+                1
+            } else {
+                superscript
+                    .chars()
+                    .map(
+                        |c| match SUPERSCRIPT_DIGITS.iter().position(|&digit| digit == c) {
+                            Some(digit) => char::from_digit(digit as u32, 10),
+                            None => Some('-'),
+                        },
+                    )
+                    .collect::<Option<String>>()?
+                    .parse()
+                    .ok()?
+            };
+            (name, exponent)
+        }
+    };
+
+    let is_name = !name.is_empty() && !name.contains(char::is_whitespace) && !name.contains('^');
+    is_name.then_some((name, exponent))
+}
+
+/// A unit expression expanded into units of base dimensions
+struct Expansion<'s> {
+    /// The factor the expanded units have to be multiplied with
+    coefficient: Ratio,
+    factors: Vec<(&'s UnitSet, String, i32)>,
+}
+
+/// A factor kept as a fraction, so reciprocals like 1/3600 don't need to be rounded
+#[derive(Clone, Copy)]
+struct Ratio {
+    numerator: Decimal,
+    denominator: Decimal,
+}
+
+impl Ratio {
+    const ONE: Self = Self {
+        numerator: Decimal::ONE,
+        denominator: Decimal::ONE,
+    };
+
+    fn multiply(self, other: Self) -> Result<Self, DimensionError> {
+        Ok(Self {
+            numerator: multiply(self.numerator, other.numerator)?,
+            denominator: multiply(self.denominator, other.denominator)?,
+        })
+    }
+
+    fn power(self, exponent: i32) -> Result<Self, DimensionError> {
+        let mut result = Self::ONE;
+        for _ in 0..exponent.unsigned_abs() {
+            result = result.multiply(self)?;
+        }
+        Ok(if exponent < 0 {
+            result.inverse()
+        } else {
+            result
+        })
+    }
+
+    fn inverse(self) -> Self {
+        Self {
+            numerator: self.denominator,
+            denominator: self.numerator,
+        }
+    }
+}
+
+impl Expansion<'_> {
+    fn dimension(&self) -> Dimension {
+        let mut dimension = BTreeMap::new();
+        for (set, _, exponent) in &self.factors {
+            *dimension
+                .entry(dimension_name(set).to_string())
+                .or_default() += exponent;
+        }
+        dimension.retain(|_, exponent| *exponent != 0);
+        Dimension(dimension)
+    }
+}
+
+/// Converts between unit expressions whose units can come from different unit sets
+pub struct Dimensions<'s> {
+    sets: Vec<&'s UnitSet>,
+}
+
+impl<'s> Dimensions<'s> {
+    pub fn new(sets: impl IntoIterator<Item = &'s UnitSet>) -> Self {
+        Self {
+            sets: sets.into_iter().collect(),
+        }
+    }
+
+    /// Converts `value` from the unit expression `start` to `end`, which need the same dimension
+    pub fn convert(
+        &self,
+        value: Decimal,
+        start: &str,
+        end: &str,
+    ) -> Result<Decimal, DimensionError> {
+        let (start, end) = (self.expand(start)?, self.expand(end)?);
+        if start.dimension() != end.dimension() {
+            return Err(DimensionError::Mismatch {
+                start: start.dimension(),
+                end: end.dimension(),
+            });
+        }
+
+        let mut factor = start.coefficient.multiply(end.coefficient.inverse())?;
+        // Every base dimension is converted to the first unit of it
+        let mut references = HashMap::new();
+        for (factors, sign) in [(&start.factors, 1), (&end.factors, -1)] {
+            for (set, unit, exponent) in factors {
+                let reference = *references
+                    .entry(dimension_name(set))
+                    .or_insert(unit.as_str());
+                factor = factor.multiply(ratio(set, unit, reference)?.power(sign * exponent)?)?;
+            }
+        }
+        // Dividing only once keeps the result exact wherever possible
+        divide(multiply(value, factor.numerator)?, factor.denominator)
+    }
+
+    /// The sets whose units the unit expression is expanded into
+    pub fn sets_of(&self, expression: &str) -> Result<Vec<&'s UnitSet>, DimensionError> {
+        Ok(self
+            .expand(expression)?
+            .factors
+            .into_iter()
+            .map(|(set, _, _)| set)
+            .unique_by(|set| &set.name)
+            .collect())
+    }
+
+    fn expand(&self, expression: &str) -> Result<Expansion<'s>, DimensionError> {
+        // Units like the alias km/h are found as they are
+        if self.sets.iter().any(|set| set.contains(expression)) {
+            return self.expand_unit(expression, 1, 0);
+        }
+
+        let mut expansion = Expansion {
+            coefficient: Ratio::ONE,
+            factors: Vec::new(),
+        };
+        for (name, exponent) in parse_unit_expression(expression)? {
+            let unit = self.expand_unit(name, exponent, 0)?;
+            expansion.coefficient = expansion.coefficient.multiply(unit.coefficient)?;
+            expansion.factors.extend(unit.factors);
+        }
+        Ok(expansion)
+    }
+
+    /// Expands a unit which may be in multiple sets, as long as it has the same dimension in all
+    fn expand_unit(
+        &self,
+        name: &str,
+        exponent: i32,
+        depth: usize,
+    ) -> Result<Expansion<'s>, DimensionError> {
+        if depth > MAX_DEPTH {
+            return Err(DimensionError::Cycle(name.to_string()));
+        }
+
+        let sets = self
+            .sets
+            .iter()
+            .filter(|set| set.contains(name))
+            .collect::<Vec<_>>();
+        let mut expansions = sets
+            .iter()
+            .map(|set| self.expand_in(set, name, exponent, depth))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if expansions.is_empty() {
+            return Err(DimensionError::UnknownUnit(name.to_string()));
+        }
+        if !expansions.iter().map(Expansion::dimension).all_equal() {
+            return Err(DimensionError::Ambiguous {
+                unit: name.to_string(),
+                sets: sets.iter().map(|set| set.name.clone()).collect(),
+            });
+        }
+        Ok(expansions.remove(0))
+    }
+
+    fn expand_in(
+        &self,
+        set: &'s UnitSet,
+        name: &str,
+        exponent: i32,
+        depth: usize,
+    ) -> Result<Expansion<'s>, DimensionError> {
+        let unit = set.resolve(name);
+        let Some((derived, expression)) = set.parsed.derived() else {
+            return Ok(Expansion {
+                coefficient: Ratio::ONE,
+                factors: vec![(set, unit, exponent)],
+            });
+        };
+
+        let mut expansion = Expansion {
+            coefficient: ratio(set, &unit, derived)?.power(exponent)?,
+            factors: Vec::new(),
+        };
+        for (name, inner) in parse_unit_expression(expression)? {
+            let unit = self.expand_unit(name, inner * exponent, depth + 1)?;
+            expansion.coefficient = expansion.coefficient.multiply(unit.coefficient)?;
+            expansion.factors.extend(unit.factors);
+        }
+        Ok(expansion)
+    }
+}
+
+/// The base dimension of a set without a derived unit, which defaults to the name of the set
+fn dimension_name(set: &UnitSet) -> &str {
+    set.parsed.dimension().unwrap_or(&set.name)
+}
+
+/// How many `reference` one `unit` is, only linear conversions are proportional and can be
+/// combined
+fn ratio(set: &UnitSet, unit: &str, reference: &str) -> Result<Ratio, DimensionError> {
+    if unit == reference {
+        return Ok(Ratio::ONE);
+    }
+    match set.parsed.linearity(unit, reference) {
+        Ok(Linearity::Linear) => {}
+        Ok(_) => return Err(DimensionError::NotProportional(unit.to_string())),
+        Err(err) => return Err(err.into()),
+    }
+    // Of 1 h = 3600 s and 1 s = 0.000277… h the one with fewer decimal places is exact
+    let forward = set.convert(Decimal::ONE, unit, reference)?;
+    let backward = set.convert(Decimal::ONE, reference, unit)?;
+    Ok(
+        if backward.normalize().scale() < forward.normalize().scale() {
+            Ratio {
+                numerator: Decimal::ONE,
+                denominator: backward,
+            }
+        } else {
+            Ratio {
+                numerator: forward,
+                denominator: Decimal::ONE,
+            }
+        },
+    )
+}
+
+fn multiply(a: Decimal, b: Decimal) -> Result<Decimal, DimensionError> {
+    a.checked_mul(b).ok_or(DimensionError::Conversion(
+        ConversionError::CalculationFailed,
+    ))
+}
+
+fn divide(a: Decimal, b: Decimal) -> Result<Decimal, DimensionError> {
+    a.checked_div(b).ok_or(DimensionError::Conversion(
+        ConversionError::CalculationFailed,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Converter;
+    use rust_decimal_macros::dec;
+
+    fn load(files: &[(&str, &str)]) -> Vec<UnitSet> {
+        let converter = files
+            .iter()
+            .fold(Converter::new(), |converter, &(name, content)| {
+                converter.with_str(name, content, None)
+            });
+        converter
+            .names()
+            .map(|name| converter.load(name).ok().unwrap())
+            .collect()
+    }
+
+    const FILES: [(&str, &str); 6] = [
+        (
+            "distance",
+            "@dimension length\nunit km aliases: kilometre\nkm -> m: x * 1000\nm -> km: x / 1000",
+        ),
+        (
+            "time",
+            "@dimension time\nh -> s: x * 3600\ns -> h: x / 3600",
+        ),
+        (
+            "mass",
+            "@dimension mass\nkg -> g: x * 1000\ng -> kg: x / 1000",
+        ),
+        (
+            "energy",
+            "@derived j = kg * m^2 / s^2\nkj -> j: x * 1000\nj -> kj: x / 1000",
+        ),
+        (
+            "temperature",
+            "@dimension temperature\nk -> c: x - 273.15\nc -> k: x + 273.15",
+        ),
+        ("loop", "@derived a = b\nb -> a: x * 2\na -> b: x / 2"),
+    ];
+
+    #[test]
+    fn test_parse_unit_expression() {
+        assert_eq!(
+            parse_unit_expression("km/h"),
+            Ok(vec![("km", 1), ("h", -1)])
+        );
+        assert_eq!(
+            parse_unit_expression("kg * m^2 / s²"),
+            Ok(vec![("kg", 1), ("m", 2), ("s", -2)])
+        );
+        assert_eq!(
+            parse_unit_expression("kg·m⁻¹ / s^-2"),
+            Ok(vec![("kg", 1), ("m", -1), ("s", 2)])
+        );
+        assert_eq!(parse_unit_expression("1/s"), Ok(vec![("s", -1)]));
+        for invalid in ["", "m/", "m^", "m^x", "k m", "*s"] {
+            assert_eq!(
+                parse_unit_expression(invalid),
+                Err(DimensionError::InvalidExpression(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let sets = load(&FILES);
+        let dimensions = Dimensions::new(&sets);
+
+        assert_eq!(dimensions.convert(dec!(36), "km/h", "m/s"), Ok(dec!(10)));
+        assert_eq!(
+            dimensions.convert(dec!(1), "kilometre^2", "m²"),
+            Ok(dec!(1000000))
+        );
+        assert_eq!(
+            dimensions.convert(dec!(2), "kj", "g * km^2 / h^2"),
+            Ok(dec!(25920000))
+        );
+        assert_eq!(dimensions.convert(dec!(5), "m/km", "1"), Ok(dec!(0.005)));
+        assert_eq!(
+            dimensions
+                .expand("kj/s")
+                .map(|expansion| expansion.dimension().to_string()),
+            Ok(String::from("length^2 * mass * time^-3"))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let sets = load(&FILES);
+        let dimensions = Dimensions::new(&sets);
+
+        assert_eq!(
+            dimensions
+                .convert(dec!(1), "km/h", "m")
+                .map_err(|err| err.to_string()),
+            Err(String::from(
+                "Can't convert length * time^-1 to length, their dimensions differ"
+            ))
+        );
+        assert_eq!(
+            dimensions.convert(dec!(1), "mi/h", "m/s"),
+            Err(DimensionError::UnknownUnit(String::from("mi")))
+        );
+        assert_eq!(
+            dimensions.convert(dec!(1), "c/s", "k/s"),
+            Err(DimensionError::NotProportional(String::from("k")))
+        );
+        assert_eq!(
+            dimensions.convert(dec!(1), "a", "b"),
+            Err(DimensionError::Cycle(String::from("b")))
+        );
+
+        // x * x maps zero to zero, but isn't proportional either
+        let squares = load(&[("squares", "a -> b: x * x\nb -> a: x / 2")]);
+        assert_eq!(
+            Dimensions::new(&squares).convert(dec!(3), "b", "a"),
+            Err(DimensionError::NotProportional(String::from("a")))
+        );
+
+        let ambiguous = load(&[
+            (
+                "speed",
+                "@derived kn = km / h\nkn -> kmh: x * 1.852\nkmh -> kn: x / 1.852",
+            ),
+            (
+                "force",
+                "@dimension force\nkn -> n: x * 1000\nn -> kn: x / 1000",
+            ),
+            FILES[0],
+            FILES[1],
+        ]);
+        assert_eq!(
+            Dimensions::new(&ambiguous).convert(dec!(1), "kn*h", "km"),
+            Err(DimensionError::Ambiguous {
+                unit: String::from("kn"),
+                sets: vec![String::from("force"), String::from("speed")]
+            })
+        );
+    }
+}
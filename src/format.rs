@@ -0,0 +1,173 @@
+use manada::{Line, ParseError, Parsed, parse_line};
+
+/// A line of a conversions file, which is formatted depending on the lines around it
+enum Formatted<'a> {
+    Blank,
+    Comment(&'a str),
+    Conversion {
+        origin: &'a str,
+        dest: &'a str,
+        value: String,
+        comment: Option<&'a str>,
+    },
+    Other {
+        code: String,
+        comment: Option<&'a str>,
+    },
+}
+
+/// Formats a conversions file: calculations are printed with normalized spacing and as few
+/// parentheses as possible, the arrows and calculations of a block are aligned, comments are kept
+/// and multiple blank lines are merged
+pub fn format(content: &str) -> Result<String, ParseError> {
+    let mut lines = Vec::new();
+    for (i, raw) in content.lines().enumerate() {
+        let trimmed = raw.trim();
+        let (code, comment) = match trimmed.find('#') {
+            Some(start) => (trimmed[..start].trim(), Some(&trimmed[start..])),
+            None => (trimmed, None),
+        };
+
+        let formatted = if code.is_empty() {
+            comment.map_or(Formatted::Blank, Formatted::Comment)
+        } else {
+            match parse_line(code).map_err(|error_kind| ParseError::new(i, error_kind))? {
+                Line::Conversion {
+                    origin,
+                    dest,
+                    value,
+                } => Formatted::Conversion {
+                    origin,
+                    dest,
+                    value: value.to_string(),
+                    comment,
+                },
+                Line::Dimension(name) => Formatted::Other {
+                    code: format!("@dimension {name}"),
+                    comment,
+                },
+                Line::Derived { unit, expression } => Formatted::Other {
+                    code: format!("@derived {unit} = {expression}"),
+                    comment,
+                },
+                Line::Include(name) => Formatted::Other {
+                    code: format!("@include {name}"),
+                    comment,
+                },
+                Line::Import(name) => Formatted::Other {
+                    code: format!("@import {name}"),
+                    comment,
+                },
+                Line::Rates(name) => Formatted::Other {
+                    code: format!("@rates {name}"),
+                    comment,
+                },
+                Line::Remove { origin, dest } => Formatted::Other {
+                    code: format!("@remove {origin} -> {dest}"),
+                    comment,
+                },
+                Line::Declaration { .. } => Formatted::Other {
+                    code: code.to_string(),
+                    comment,
+                },
+            }
+        };
+
+        let is_redundant_blank = matches!(formatted, Formatted::Blank)
+            && matches!(lines.last(), None | Some(Formatted::Blank));
+        if !is_redundant_blank {
+            lines.push(formatted);
+        }
+    }
+    if let Some(Formatted::Blank) = lines.last() {
+        lines.pop();
+    }
+
+    let mut formatted = String::new();
+    for block in lines.split(|line| matches!(line, Formatted::Blank)) {
+        if !formatted.is_empty() {
+            formatted.push('\n');
+        }
+        format_block(block, &mut formatted);
+    }
+    Ok(formatted)
+}
+
+fn format_block(block: &[Formatted], out: &mut String) {
+    let conversions = block.iter().filter_map(|line| match line {
+        Formatted::Conversion { origin, dest, .. } => Some((origin, dest)),
+        _ => None,
+    });
+    let (origin_width, dest_width) =
+        conversions.fold((0, 0), |(origin_width, dest_width), (origin, dest)| {
+            (
+                origin_width.max(origin.chars().count()),
+                dest_width.max(dest.chars().count() + 1),
+            )
+        });
+
+    for line in block {
+        let (code, comment) = match line {
+            Formatted::Blank => continue,
+            Formatted::Comment(comment) => (String::new(), Some(*comment)),
+            Formatted::Conversion {
+                origin,
+                dest,
+                value,
+                comment,
+            } => (
+                format!(
+                    "{origin:origin_width$} -> {:dest_width$} {value}",
+                    format!("{dest}:")
+                ),
+                *comment,
+            ),
+            Formatted::Other { code, comment } => (code.clone(), *comment),
+        };
+
+        match (code.is_empty(), comment) {
+            (true, Some(comment)) => out.push_str(comment),
+            (false, Some(comment)) => out.push_str(&format!("{code} {comment}")),
+            (_, None) => out.push_str(&code),
+        }
+        out.push('\n');
+    }
+}
+
+/// Whether both files result in the same conversions
+pub fn is_equivalent(original: &str, formatted: &str) -> bool {
+    match (Parsed::try_new(original), Parsed::try_new(formatted)) {
+        (Ok(original), Ok(formatted)) => original.conversions() == formatted.conversions(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let unformatted = "\n\n# Metric\nkm -> m: x*1000\n   m->dm: x * 10\nnm -> m: ((x * 1852)) # nautical\n\n\n\n# Fahrenheit\nf -> c: (x - 32) * 5/9\n@include   ./imperial\n\n";
+        let formatted = "# Metric\nkm -> m:  x * 1000\nm  -> dm: x * 10\nnm -> m:  x * 1852 # nautical\n\n# Fahrenheit\nf -> c: (x - 32) * 5 / 9\n@include ./imperial\n";
+
+        // "m->dm" is missing the spaces around the arrow
+        assert!(format(unformatted).is_err());
+
+        let unformatted = unformatted.replace("m->dm", "m -> dm");
+        assert_eq!(format(&unformatted).ok().unwrap(), formatted);
+        assert_eq!(format(formatted).ok().unwrap(), formatted);
+        assert!(is_equivalent(&unformatted, formatted));
+    }
+
+    include!("test_files.rs");
+
+    #[test]
+    fn test_round_trip() {
+        for file in TEST_FILES {
+            let formatted = format(file).ok().unwrap();
+            assert!(is_equivalent(file, &formatted));
+            assert_eq!(format(&formatted).ok().unwrap(), formatted);
+        }
+    }
+}
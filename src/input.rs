@@ -0,0 +1,402 @@
+use derive_more::Display;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Quantity {
+    pub value: Decimal,
+    pub unit: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Display)]
+pub enum QuantityParseError {
+    #[display("{_0} doesn't start with a number")]
+    MissingNumber(String),
+    #[display("{_0} isn't a number")]
+    InvalidNumber(String),
+    #[display("There is no unit after {_0}")]
+    MissingUnit(String),
+    #[display("The range {_0} ends before it starts")]
+    ReversedRange(String),
+}
+
+impl std::error::Error for QuantityParseError {}
+
+/// A possible split of the input into a number and a unit
+struct Candidate<'a> {
+    number: String,
+    unit: &'a str,
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Consumes digits into `buf`, allowing `_`, `,` and `'` as thousands separators as long as
+    /// they are followed by exactly three digits
+    fn digits(&mut self, buf: &mut String, allow_separators: bool) -> usize {
+        let mut count = 0;
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => {
+                    buf.push(c);
+                    self.bump();
+                    count += 1;
+                }
+                Some('_' | ',' | '\'') if allow_separators && count > 0 && self.is_group() => {
+                    self.bump();
+                }
+                _ => return count,
+            }
+        }
+    }
+
+    fn is_group(&self) -> bool {
+        (1..=3).all(|n| self.peek_nth(n).is_some_and(|c| c.is_ascii_digit()))
+            && !self.peek_nth(4).is_some_and(|c| c.is_ascii_digit())
+    }
+
+    /// Lexes the sign, integer and fractional part of the number
+    fn mantissa(&mut self) -> Result<String, QuantityParseError> {
+        self.skip_whitespace();
+
+        let mut mantissa = String::new();
+        if let Some(sign @ ('+' | '-')) = self.peek() {
+            self.bump();
+            self.skip_whitespace();
+            if sign == '-' {
+                mantissa.push('-');
+            }
+        }
+
+        let start = self.pos;
+        let mut digit_count = self.digits(&mut mantissa, true);
+        if self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            mantissa.push('.');
+            digit_count += self.digits(&mut mantissa, false);
+        }
+        if digit_count == 0 {
+            return Err(QuantityParseError::MissingNumber(self.input.to_string()));
+        }
+        // A separator before fewer or more than three digits, like the decimal comma of 1,5
+        let is_separator = matches!(self.peek(), Some('_' | ',' | '\''));
+        if is_separator && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+            let number = self.input[start..]
+                .split(|c: char| !c.is_ascii_digit() && !"_,'.".contains(c))
+                .next()
+                .unwrap_or_default();
+            return Err(QuantityParseError::InvalidNumber(number.trim().to_string()));
+        }
+
+        Ok(mantissa)
+    }
+
+    /// Lexes an exponent like `e-3`, leaving the input as it is if there are no digits after the
+    /// `e`
+    fn exponent(&mut self) -> Option<String> {
+        let mut lookahead = Lexer {
+            input: self.input,
+            pos: self.pos,
+        };
+        if !matches!(lookahead.bump(), Some('e' | 'E')) {
+            return None;
+        }
+        let mut exponent = String::new();
+        if let Some(sign @ ('+' | '-')) = lookahead.peek() {
+            lookahead.bump();
+            exponent.push(sign);
+        }
+        if lookahead.digits(&mut exponent, false) == 0 {
+            return None;
+        }
+        self.pos = lookahead.pos;
+        Some(exponent)
+    }
+
+    /// Lexes the mantissa and the exponent of a number which isn't followed by a unit
+    fn number(&mut self) -> Result<String, QuantityParseError> {
+        let mantissa = self.mantissa()?;
+        Ok(match self.exponent() {
+            Some(exponent) => format!("{mantissa}e{exponent}"),
+            None => mantissa,
+        })
+    }
+
+    /// Lexes the number at the start of the input, returning every valid split
+    fn candidates(mut self) -> Result<Vec<Candidate<'a>>, QuantityParseError> {
+        let mantissa = self.mantissa()?;
+
+        let mut candidates = vec![Candidate {
+            number: mantissa.clone(),
+            unit: self.input[self.pos..].trim(),
+        }];
+
+        if let Some(exponent) = self.exponent() {
+            candidates.insert(
+                0,
+                Candidate {
+                    number: format!("{mantissa}e{exponent}"),
+                    unit: self.input[self.pos..].trim(),
+                },
+            );
+        }
+
+        Ok(candidates)
+    }
+}
+
+impl Candidate<'_> {
+    fn quantity(self) -> Result<Quantity, QuantityParseError> {
+        if self.unit.is_empty() {
+            return Err(QuantityParseError::MissingUnit(self.number));
+        }
+
+        Ok(Quantity {
+            value: decimal(self.number)?,
+            unit: self.unit.to_string(),
+        })
+    }
+}
+
+/// Converts a lexed number into a decimal
+fn decimal(number: String) -> Result<Decimal, QuantityParseError> {
+    let value = if number.contains('e') {
+        Decimal::from_scientific(&number)
+    } else {
+        Decimal::from_str(&number)
+    };
+    value.map_err(|_| QuantityParseError::InvalidNumber(number))
+}
+
+/// Splits an input like `5 km`, `-1.5e3m` or `1,000 m2` into its value and unit.
+///
+/// When the input can be split in multiple ways (`1e3m` could be `1` of `e3m`), the split which
+/// results in the longest unit out of `units` wins. If no split results in a known unit, the
+/// longest number is used.
+pub fn parse_quantity<'a>(
+    input: &str,
+    units: impl IntoIterator<Item = &'a str>,
+) -> Result<Quantity, QuantityParseError> {
+    let mut candidates = Lexer::new(input).candidates()?;
+    let units = units.into_iter().collect::<Vec<_>>();
+
+    let known = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| units.contains(&candidate.unit))
+        .max_by_key(|(_, candidate)| candidate.unit.len())
+        .map(|(i, _)| i);
+
+    candidates.swap_remove(known.unwrap_or(0)).quantity()
+}
+
+/// Splits a measured value like `5.2±0.1km` or `5.2 +- 0.1 km` into the quantity `5.2km` and
+/// its uncertainty, inputs without one are returned as they are
+pub fn split_uncertainty(input: &str) -> Result<(String, Option<Decimal>), QuantityParseError> {
+    let Some((value, rest)) = input.split_once('±').or_else(|| input.split_once("+-")) else {
+        return Ok((input.to_string(), None));
+    };
+
+    let mut lexer = Lexer::new(value);
+    let number = lexer
+        .number()
+        .ok()
+        .filter(|_| lexer.input[lexer.pos..].trim().is_empty())
+        .ok_or_else(|| QuantityParseError::InvalidNumber(value.trim().to_string()))?;
+    let value = decimal(number)?;
+    let Quantity {
+        value: uncertainty,
+        unit,
+    } = parse_quantity(rest, [])?;
+    Ok((format!("{value}{unit}"), Some(uncertainty.abs())))
+}
+
+/// Splits a range like `10..20km` or `10-20km` into the quantity `10km` and the upper end of the
+/// range, inputs without one are returned as they are
+pub fn split_range(input: &str) -> Result<(String, Option<Decimal>), QuantityParseError> {
+    let mut lexer = Lexer::new(input);
+    let Ok(low) = lexer.number() else {
+        return Ok((input.to_string(), None));
+    };
+    let rest = &input[lexer.pos..];
+    let Some(rest) = rest
+        .trim_start()
+        .strip_prefix("..")
+        .or_else(|| rest.strip_prefix('-'))
+    else {
+        return Ok((input.to_string(), None));
+    };
+
+    let low = decimal(low)?;
+    let Quantity { value: high, unit } = parse_quantity(rest, [])?;
+    if low > high {
+        return Err(QuantityParseError::ReversedRange(input.trim().to_string()));
+    }
+    Ok((format!("{low}{unit}"), Some(high)))
+}
+
+/// The part of a partially typed input which is a number, without an exponent
+pub fn number_prefix(input: &str) -> Option<&str> {
+    let mut lexer = Lexer::new(input);
+    lexer.mantissa().ok()?;
+    Some(&input[..lexer.pos])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const UNITS: [&str; 6] = ["km", "m", "m2", "co2", "eb", "e3m"];
+
+    fn parse(input: &str) -> Quantity {
+        parse_quantity(input, UNITS).expect("parsing failed")
+    }
+
+    fn quantity(value: Decimal, unit: &str) -> Quantity {
+        Quantity {
+            value,
+            unit: unit.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_whitespace_and_signs() {
+        assert_eq!(parse("5km"), quantity(dec!(5), "km"));
+        assert_eq!(parse("5 km"), quantity(dec!(5), "km"));
+        assert_eq!(parse("  -5.5   km "), quantity(dec!(-5.5), "km"));
+        assert_eq!(parse("+3m"), quantity(dec!(3), "m"));
+        assert_eq!(parse(".5km"), quantity(dec!(0.5), "km"));
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        assert_eq!(parse("1e3 km"), quantity(dec!(1000), "km"));
+        assert_eq!(parse("1.5E-3km"), quantity(dec!(0.0015), "km"));
+        assert_eq!(parse("2eb"), quantity(dec!(2), "eb"));
+    }
+
+    #[test]
+    fn test_longest_unit_wins() {
+        assert_eq!(parse("1e3m"), quantity(dec!(1), "e3m"));
+        assert_eq!(
+            parse_quantity("1e3m", ["m"]).unwrap(),
+            quantity(dec!(1000), "m")
+        );
+    }
+
+    #[test]
+    fn test_digits_in_units() {
+        assert_eq!(parse("5m2"), quantity(dec!(5), "m2"));
+        assert_eq!(parse("12 co2"), quantity(dec!(12), "co2"));
+    }
+
+    #[test]
+    fn test_thousands_separators() {
+        assert_eq!(parse("1,000km"), quantity(dec!(1000), "km"));
+        assert_eq!(parse("1_000_000 m"), quantity(dec!(1000000), "m"));
+        assert_eq!(parse("1'000.5 m"), quantity(dec!(1000.5), "m"));
+        assert_eq!(
+            parse_quantity("1,5km", UNITS),
+            Err(QuantityParseError::InvalidNumber(String::from("1,5")))
+        );
+        assert_eq!(
+            parse_quantity("1,000,5 m", UNITS),
+            Err(QuantityParseError::InvalidNumber(String::from("1,000,5")))
+        );
+    }
+
+    #[test]
+    fn test_split_uncertainty() {
+        let split = |input| split_uncertainty(input).unwrap();
+        assert_eq!(split("5.2±0.1km"), (String::from("5.2km"), Some(dec!(0.1))));
+        assert_eq!(
+            split("-5.2 +- 0.1 km"),
+            (String::from("-5.2km"), Some(dec!(0.1)))
+        );
+        assert_eq!(
+            split("1.5e3 ± 2e1 m"),
+            (String::from("1500m"), Some(dec!(20)))
+        );
+        assert_eq!(split("1,000±5m"), (String::from("1000m"), Some(dec!(5))));
+        assert_eq!(split("5km"), (String::from("5km"), None));
+        assert_eq!(
+            split_uncertainty("5km±0.1km"),
+            Err(QuantityParseError::InvalidNumber(String::from("5km")))
+        );
+        assert_eq!(
+            split_uncertainty("5±0.1"),
+            Err(QuantityParseError::MissingUnit(String::from("0.1")))
+        );
+    }
+
+    #[test]
+    fn test_split_range() {
+        let split = |input| split_range(input).unwrap();
+        assert_eq!(split("10..20km"), (String::from("10km"), Some(dec!(20))));
+        assert_eq!(split("10-20km"), (String::from("10km"), Some(dec!(20))));
+        assert_eq!(split("-10..-5 c"), (String::from("-10c"), Some(dec!(-5))));
+        assert_eq!(split("-40--10c"), (String::from("-40c"), Some(dec!(-10))));
+        assert_eq!(split("1e-3m"), (String::from("1e-3m"), None));
+        assert_eq!(
+            split("1e3..2.5e3 m"),
+            (String::from("1000m"), Some(dec!(2500)))
+        );
+        assert_eq!(
+            split("1e-3-2e-3m"),
+            (String::from("0.001m"), Some(dec!(0.002)))
+        );
+        assert_eq!(split("5 - km"), (String::from("5 - km"), None));
+        assert_eq!(
+            split_range("20..10km"),
+            Err(QuantityParseError::ReversedRange(String::from("20..10km")))
+        );
+        assert_eq!(
+            split_range("10..km"),
+            Err(QuantityParseError::MissingNumber(String::from("km")))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_quantity("km", UNITS),
+            Err(QuantityParseError::MissingNumber(String::from("km")))
+        );
+        assert_eq!(
+            parse_quantity("5", UNITS),
+            Err(QuantityParseError::MissingUnit(String::from("5")))
+        );
+        assert_eq!(
+            parse_quantity("-", UNITS),
+            Err(QuantityParseError::MissingNumber(String::from("-")))
+        );
+    }
+}
@@ -1,25 +1,146 @@
+//! Unit conversions defined in plain text files
+//!
+//! A [`Converter`] knows all unit sets, either found in the search directories or added from files
+//! and strings, and loads them as [`UnitSet`]s which convert between their units:
+//!
+//! ```
+//! use manada::Converter;
+//! use rust_decimal::Decimal;
+//!
+//! let converter = Converter::new().with_str(
+//!     "distance",
+//!     "unit km aliases: kilometre\nkm -> m: x * 1000\nm -> km: x / 1000",
+//!     None,
+//! );
+//! let distance = converter.load("distance").unwrap();
+//! assert_eq!(distance.units(), vec!["km", "m"]);
+//! assert_eq!(distance.resolve("kilometre"), "km");
+//! assert_eq!(
+//!     distance.convert(Decimal::from(5), "kilometre", "m").unwrap(),
+//!     Decimal::from(5000)
+//! );
+//! ```
+
+mod bundled;
+mod calculation;
+mod config;
+mod converter;
+mod declaration;
+mod dimension;
+mod input;
+mod parse;
+mod rates;
+
+pub use calculation::{
+    Linearity, Operator, Value,
+    parser::CalculationParseError,
+    token::{Token, TokenizeError},
+};
+pub use config::{AliasError, AliasIndex, Config, NewConfigError};
+pub use converter::{Converter, LoadError, UnitSet};
+pub use declaration::{DeclarationError, UnitMetadata};
+pub use dimension::{Dimension, DimensionError, is_expression};
+pub use input::{
+    Quantity, QuantityParseError, number_prefix, parse_quantity, split_range, split_uncertainty,
+};
+pub use parse::{
+    ConversionError, Line, ParseError, ParseErrorKind, Parsed, Source, SourceError, parse_line,
+};
+pub use rates::{Date, Rates, RatesError};
+
 use derive_more::Display;
-use std::{env, path::{Path, PathBuf}};
+use itertools::Itertools;
+use std::{
+    env,
+    error::Error,
+    fs::read_to_string,
+    io,
+    path::{Path, PathBuf},
+};
 
-pub fn file_path<P: AsRef<Path>>(file_name: P) -> Result<PathBuf, NoFilePathError> {
-    let config_dir_file = dirs::config_dir().map(|dir| dir.join("manada").join(&file_name));
-    let etc_file = env::var("MANADA_CONFIG")
-        .map_or(PathBuf::from("/etc/manada"), PathBuf::from)
-        .join(&file_name);
-
-    match (config_dir_file, etc_file) {
-        (Some(file), _) if file.exists() => Ok(file),
-        (_, file) if file.exists() => Ok(file),
-        (Some(home_file), etc_file) => Err(NoFilePathError::NeitherHomeNorEtc(home_file, etc_file)),
-        // Very unlikly but it can happen, if there is no home config dir
-        (None, etc_file) => Err(NoFilePathError::NoEtc(etc_file)),
+/// The directories unit sets are searched in, sorted by priority:
+///
+/// 1. The nearest `.manada` directory in the current directory or its ancestors
+/// 2. The user config directory, e.g. `~/.config/manada`
+/// 3. Every entry of `MANADA_PATH`
+/// 4. The XDG data directories, e.g. `~/.local/share/manada` and `/usr/share/manada`
+/// 5. `MANADA_CONFIG`, which defaults to `/etc/manada`
+///
+/// The unit sets compiled in by the `bundled` feature come after all of them.
+pub fn search_dirs() -> Vec<PathBuf> {
+    let project_dir = env::current_dir().ok().and_then(|cwd| {
+        cwd.ancestors()
+            .map(|dir| dir.join(".manada"))
+            .find(|dir| dir.is_dir())
+    });
+    let home_dir = dirs::config_dir().map(|dir| dir.join("manada"));
+    let manada_path = env::var_os("MANADA_PATH")
+        .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let data_dirs = dirs::data_dir().into_iter().chain(xdg_data_dirs());
+    let etc_dir = env::var("MANADA_CONFIG").map_or(PathBuf::from("/etc/manada"), PathBuf::from);
+
+    project_dir
+        .into_iter()
+        .chain(home_dir)
+        .chain(manada_path)
+        .chain(data_dirs.map(|dir| dir.join("manada")))
+        .chain([etc_dir])
+        .unique()
+        .collect()
+}
+
+fn xdg_data_dirs() -> Vec<PathBuf> {
+    let dirs = env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    env::split_paths(&dirs).collect()
+}
+
+/// Every existing file called `file_name` in the search directories followed by the bundled one,
+/// sorted by priority. Bundled files don't exist on disk, so they have to be read by [`read_file`].
+pub fn file_paths<P: AsRef<Path>>(file_name: P) -> Vec<PathBuf> {
+    let bundled = file_name
+        .as_ref()
+        .to_str()
+        .filter(|name| bundled::file(name).is_some())
+        .map(bundled::path);
+    search_dirs()
+        .into_iter()
+        .map(|dir| dir.join(&file_name))
+        .filter(|file| file.exists())
+        .chain(bundled)
+        .collect()
+}
+
+/// Reads a file of [`file_paths`], which can also be a bundled one
+pub fn read_file(path: &Path) -> io::Result<String> {
+    match bundled::content(path) {
+        Some(content) => Ok(content.to_string()),
+        None => read_to_string(path),
     }
 }
 
-#[derive(Display)]
-pub enum NoFilePathError {
-    #[display("Neither {} nor {} exist", _0.display(), _1.display())]
-    NeitherHomeNorEtc(PathBuf, PathBuf),
-    #[display("{} doesn't exist", _0.display())]
-    NoEtc(PathBuf),
+/// The file with the highest priority called `file_name`
+pub fn file_path<P: AsRef<Path>>(file_name: P) -> Result<PathBuf, NoFilePathError> {
+    file_paths(&file_name)
+        .into_iter()
+        .next()
+        .ok_or_else(|| NoFilePathError {
+            file_name: file_name.as_ref().to_path_buf(),
+            searched: search_dirs(),
+        })
 }
+
+#[derive(Display, Debug)]
+#[display(
+    "{} doesn't exist in any of {}",
+    file_name.display(),
+    searched.iter().map(|dir| dir.display()).join(", ")
+)]
+pub struct NoFilePathError {
+    pub file_name: PathBuf,
+    pub searched: Vec<PathBuf>,
+}
+
+impl Error for NoFilePathError {}
@@ -0,0 +1,507 @@
+use ansi_term::Colour;
+use itertools::Itertools;
+use manada::{Converter, Linearity, LoadError, Parsed};
+use rust_decimal::Decimal;
+use std::{cmp::Reverse, collections::HashMap, path::PathBuf};
+use toml::{Table, Value};
+
+const CONFIG_KEYS: [&str; 4] = ["aliases", "case_insensitive", "max_rate_age", "names"];
+const NAME_KEYS: [&str; 3] = ["singular", "plural", "locales"];
+const LOCALE_KEYS: [&str; 2] = ["singular", "plural"];
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The config file the problem is in, problems of the conversions files have none
+    pub path: Option<PathBuf>,
+}
+
+impl Diagnostic {
+    fn error(message: String, path: Option<&PathBuf>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            path: path.cloned(),
+        }
+    }
+
+    fn warning(message: String, path: Option<&PathBuf>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            path: path.cloned(),
+        }
+    }
+
+    fn print(&self) {
+        let label = match self.severity {
+            Severity::Error => Colour::Red.paint("error: "),
+            Severity::Warning => Colour::Yellow.paint("warning: "),
+        };
+        eprintln!("{label}{}", self.message);
+        if let Some(path) = &self.path {
+            eprintln!("  {} {}", Colour::Blue.paint("-->"), path.display());
+        }
+    }
+}
+
+/// Lints a unit set together with its config files, errors if the set can't be loaded
+pub fn lint_set(converter: &Converter, name: &str) -> Result<Vec<Diagnostic>, LoadError> {
+    let (_, parsed) = converter.parse(name)?;
+
+    let configs = manada::file_paths(format!("{name}.toml"))
+        .into_iter()
+        .map(|path| {
+            let content = manada::read_file(&path);
+            (path, content)
+        })
+        .collect::<Vec<_>>();
+    let mut diagnostics = configs
+        .iter()
+        .filter_map(|(path, content)| {
+            let error = content.as_ref().err()?;
+            Some(Diagnostic::error(
+                format!("Can't read file ({})", error.kind()),
+                Some(path),
+            ))
+        })
+        .collect::<Vec<_>>();
+    let configs = configs
+        .into_iter()
+        .filter_map(|(path, content)| Some((path, content.ok()?)))
+        .collect::<Vec<_>>();
+    diagnostics.extend(lint(&parsed, &configs));
+    if crate::completion::SUBCOMMANDS.contains(&name) {
+        diagnostics.push(Diagnostic::warning(
+            format!(
+                "The unit set is shadowed by the {name} subcommand, convert with `manada -- {name} <VALUE> <DESTINATION>`"
+            ),
+            None,
+        ));
+    }
+    for import in parsed.imports() {
+        if !converter.names().any(|name| name == import) {
+            diagnostics.push(Diagnostic::error(
+                format!("The imported unit set {import} doesn't exist"),
+                None,
+            ));
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// Prints the diagnostics of a unit set, returns whether it passed
+pub fn report(diagnostics: &[Diagnostic], strict: bool) -> bool {
+    diagnostics.iter().for_each(Diagnostic::print);
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    eprintln!("{errors} errors, {warnings} warnings\n");
+
+    errors == 0 && (!strict || warnings == 0)
+}
+
+/// Checks a parsed unit set and the contents of its config files, which are sorted by priority
+pub fn lint(parsed: &Parsed, configs: &[(PathBuf, String)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    lint_graph(parsed, &mut diagnostics);
+
+    // Every alias with its unit and the config file it is in
+    let file_aliases = parsed
+        .aliases()
+        .map(|(alias, unit)| (alias.to_string(), unit.to_string(), None))
+        .collect::<Vec<_>>();
+    lint_aliases(parsed, &file_aliases, &mut diagnostics);
+    lint_collisions(&[], &file_aliases, &mut diagnostics);
+
+    // Like when loading, the aliases of a unit in a config replace those of the lower configs and
+    // take their aliases away from other units
+    let mut config_aliases = Vec::<(String, String, Option<PathBuf>)>::new();
+    for (path, content) in configs.iter().rev() {
+        let table = match content.parse::<Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                diagnostics.push(Diagnostic::error(
+                    format!("Can't parse the file: {}", err.message()),
+                    Some(path),
+                ));
+                continue;
+            }
+        };
+        let mut layer = Vec::new();
+        lint_config(parsed, path, &table, &mut layer, &mut diagnostics);
+        lint_aliases(parsed, &layer, &mut diagnostics);
+        lint_collisions(&[], &layer, &mut diagnostics);
+
+        config_aliases.retain(|(alias, unit, _)| {
+            !layer
+                .iter()
+                .any(|(other_alias, other_unit, _)| other_alias == alias || other_unit == unit)
+        });
+        // Collisions and duplicates within the layer are already reported
+        config_aliases.extend(layer.into_iter().unique_by(|(alias, _, _)| alias.clone()));
+    }
+    lint_collisions(&file_aliases, &config_aliases, &mut diagnostics);
+
+    diagnostics.sort_by_key(|diagnostic| Reverse(diagnostic.severity));
+    diagnostics
+}
+
+fn lint_graph(parsed: &Parsed, diagnostics: &mut Vec<Diagnostic>) {
+    let components = parsed.components();
+    if let [main, others @ ..] = components.as_slice() {
+        for other in others {
+            diagnostics.push(Diagnostic::warning(
+                format!(
+                    "{} can't be converted to or from {}",
+                    other.join(", "),
+                    main.join(", ")
+                ),
+                None,
+            ));
+        }
+    }
+
+    if let Some((unit, _)) = parsed.derived()
+        && !parsed.contains(unit)
+    {
+        diagnostics.push(Diagnostic::error(
+            format!("The derived unit {unit} doesn't exist"),
+            None,
+        ));
+    }
+
+    for (origin, dest) in parsed.one_way_conversions() {
+        diagnostics.push(Diagnostic::warning(
+            format!("{origin} can be converted to {dest} but not back"),
+            None,
+        ));
+    }
+
+    // Linear and affine conversions are determined by two values, so converting them back and
+    // forth shows whether they are inverses. Rounding makes small differences acceptable.
+    let tolerance = Decimal::new(1, 9);
+    let conversions = parsed.conversions();
+    for &(origin, dest, calc) in &conversions {
+        let Some(&(_, _, inverse)) =
+            conversions
+                .iter()
+                .find(|&&(inverse_origin, inverse_dest, _)| {
+                    inverse_origin == dest && inverse_dest == origin
+                })
+        else {
+            continue;
+        };
+        let is_nonlinear = [calc, inverse]
+            .iter()
+            .any(|calc| calc.linearity() == Linearity::Nonlinear);
+        if origin > dest || is_nonlinear {
+            continue;
+        }
+
+        let round_trips = [Decimal::ZERO, Decimal::ONE].into_iter().all(|x| {
+            calc.evaluate(x)
+                .and_then(|converted| inverse.evaluate(converted))
+                .is_some_and(|back| (back - x).abs() <= tolerance)
+        });
+        if !round_trips {
+            diagnostics.push(Diagnostic::warning(
+                format!("{origin} -> {dest} and {dest} -> {origin} don't undo each other"),
+                None,
+            ));
+        }
+    }
+}
+
+fn lint_config(
+    parsed: &Parsed,
+    path: &PathBuf,
+    table: &Table,
+    aliases: &mut Vec<(String, String, Option<PathBuf>)>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let unused = |key: &str| Diagnostic::warning(format!("The key {key} is unused"), Some(path));
+    let exists = |unit: &str| parsed.contains(unit);
+
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("aliases", Value::Table(units)) => {
+                for (unit, unit_aliases) in units {
+                    let Some(unit_aliases) = unit_aliases.as_array() else {
+                        diagnostics.push(Diagnostic::error(
+                            format!("The aliases of {unit} aren't a list"),
+                            Some(path),
+                        ));
+                        continue;
+                    };
+                    for alias in unit_aliases {
+                        match alias.as_str() {
+                            Some(alias) => {
+                                aliases.push((alias.to_string(), unit.clone(), Some(path.clone())))
+                            }
+                            None => diagnostics.push(Diagnostic::error(
+                                format!("The alias {alias} of {unit} isn't a string"),
+                                Some(path),
+                            )),
+                        }
+                    }
+                }
+            }
+            ("case_insensitive", Value::Boolean(_)) => {}
+            ("max_rate_age", Value::Integer(days)) if u32::try_from(*days).is_ok() => {}
+            ("names", Value::Table(units)) => {
+                for (unit, names) in units {
+                    if !exists(unit) {
+                        diagnostics.push(Diagnostic::warning(
+                            format!("There are names for {unit}, which doesn't exist"),
+                            Some(path),
+                        ));
+                    }
+                    let Some(names) = names.as_table() else {
+                        diagnostics.push(Diagnostic::error(
+                            format!("The names of {unit} aren't a table"),
+                            Some(path),
+                        ));
+                        continue;
+                    };
+                    for (key, value) in names {
+                        if !NAME_KEYS.contains(&key.as_str()) {
+                            diagnostics.push(unused(&format!("names.{unit}.{key}")));
+                        } else if key == "locales" {
+                            let locales = value.as_table().into_iter().flatten();
+                            for (locale, locale_names) in locales {
+                                let locale_keys = locale_names.as_table().into_iter().flatten();
+                                for (key, _) in locale_keys {
+                                    if !LOCALE_KEYS.contains(&key.as_str()) {
+                                        diagnostics.push(unused(&format!(
+                                            "names.{unit}.locales.{locale}.{key}"
+                                        )));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            (key, _) if CONFIG_KEYS.contains(&key) => diagnostics.push(Diagnostic::error(
+                format!("The key {key} has the wrong type"),
+                Some(path),
+            )),
+            (key, _) => diagnostics.push(unused(key)),
+        }
+    }
+}
+
+/// Checks that the aliases belong to existing units and don't shadow other units
+fn lint_aliases(
+    parsed: &Parsed,
+    aliases: &[(String, String, Option<PathBuf>)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (alias, unit, path) in aliases {
+        let path = path.as_ref();
+        if !parsed.contains(unit) {
+            diagnostics.push(Diagnostic::error(
+                format!("The alias {alias} belongs to {unit}, which doesn't exist"),
+                path,
+            ));
+        }
+        if alias != unit && parsed.contains(alias) {
+            diagnostics.push(Diagnostic::error(
+                format!("The alias {alias} of {unit} is also the name of another unit"),
+                path,
+            ));
+        }
+    }
+}
+
+/// Checks that `aliases` are neither used twice nor used by `known`, which are already checked
+fn lint_collisions(
+    known: &[(String, String, Option<PathBuf>)],
+    aliases: &[(String, String, Option<PathBuf>)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut seen = known
+        .iter()
+        .map(|(alias, unit, _)| (alias.as_str(), unit.as_str()))
+        .collect::<HashMap<_, _>>();
+    for (alias, unit, path) in aliases {
+        let path = path.as_ref();
+        match seen.insert(alias, unit) {
+            Some(other) if other != unit => diagnostics.push(Diagnostic::error(
+                format!("The alias {alias} is used for both {other} and {unit}"),
+                path,
+            )),
+            Some(_) => diagnostics.push(Diagnostic::warning(
+                format!("The alias {alias} of {unit} is duplicated"),
+                path,
+            )),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(conversions: &str, config: &str) -> Vec<(Severity, String)> {
+        let parsed = Parsed::try_new(conversions).ok().unwrap();
+        let configs = [(PathBuf::from("set.toml"), config.to_string())];
+        lint(&parsed, &configs)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message))
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_set() {
+        let conversions = "unit km aliases: kilometer\nkm -> m: x * 1000\nm -> km: x / 1000";
+        let config =
+            "case_insensitive = true\n[aliases]\nm = [\"meter\"]\n[names.m]\nsingular = \"metre\"";
+        assert_eq!(messages(conversions, config), vec![]);
+    }
+
+    #[test]
+    fn test_alias_layers() {
+        let parsed = Parsed::try_new(
+            "nmi -> m: x * 1852\nm -> nmi: x / 1852\nmi -> m: x * 1609.344\nm -> mi: x / 1609.344",
+        )
+        .unwrap();
+        let configs = [
+            (
+                PathBuf::from("high.toml"),
+                String::from("[aliases]\nnmi = [\"miles\"]"),
+            ),
+            (
+                PathBuf::from("low.toml"),
+                String::from("[aliases]\nmi = [\"miles\", \"mile\"]\nm = [\"meter\"]"),
+            ),
+        ];
+        assert_eq!(lint(&parsed, &configs), vec![]);
+
+        let configs = [(
+            PathBuf::from("high.toml"),
+            String::from("[aliases]\nnmi = [\"miles\"]\nmi = [\"miles\"]"),
+        )];
+        assert_eq!(
+            lint(&parsed, &configs),
+            vec![Diagnostic::error(
+                String::from("The alias miles is used for both mi and nmi"),
+                Some(&PathBuf::from("high.toml"))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_max_rate_age() {
+        let conversions = "eur -> usd: x * 1.08\nusd -> eur: x / 1.08";
+        assert_eq!(messages(conversions, "max_rate_age = 30"), vec![]);
+        assert_eq!(
+            messages(conversions, "max_rate_age = -1"),
+            vec![(
+                Severity::Error,
+                String::from("The key max_rate_age has the wrong type")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_graph() {
+        assert_eq!(
+            messages(
+                "km -> m: x * 1000\nc -> f: x * 9/5 + 32\nf -> c: (x - 32) * 5/9",
+                ""
+            ),
+            vec![
+                (
+                    Severity::Warning,
+                    String::from("km can be converted to m but not back")
+                ),
+                (
+                    Severity::Warning,
+                    String::from("km, m can't be converted to or from c, f")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inverses() {
+        assert_eq!(
+            messages("m -> km: x / 1000\nkm -> m: x * 100", ""),
+            vec![(
+                Severity::Warning,
+                String::from("km -> m and m -> km don't undo each other")
+            )]
+        );
+        assert_eq!(
+            messages("c -> f: x * 9/5 + 32\nf -> c: (x - 32) * 5/9", ""),
+            vec![]
+        );
+        assert_eq!(
+            messages("l100km -> kml: 100 / x\nkml -> l100km: 100 / x", ""),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let conversions = "unit km aliases: kilo\nkm -> m: x * 1000\nm -> km: x / 1000";
+        let config = "[aliases]\nm = [\"meter\", \"meter\", \"kilo\", \"km\"]\nmm = [\"milli\"]";
+        assert_eq!(
+            messages(conversions, config),
+            vec![
+                (
+                    Severity::Warning,
+                    String::from("The alias meter of m is duplicated")
+                ),
+                (
+                    Severity::Error,
+                    String::from("The alias kilo is used for both km and m")
+                ),
+                (
+                    Severity::Error,
+                    String::from("The alias km of m is also the name of another unit")
+                ),
+                (
+                    Severity::Error,
+                    String::from("The alias milli belongs to mm, which doesn't exist")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_keys() {
+        let config = "alias = 1\ncase_insensitive = \"yes\"\n[names.m]\nsingle = \"metre\"\n[names.cm]\nsingular = \"centimetre\"";
+        assert_eq!(
+            messages("m -> m: x", config),
+            vec![
+                (Severity::Warning, String::from("The key alias is unused")),
+                (
+                    Severity::Warning,
+                    String::from("The key names.m.single is unused")
+                ),
+                (
+                    Severity::Warning,
+                    String::from("There are names for cm, which doesn't exist")
+                ),
+                (
+                    Severity::Error,
+                    String::from("The key case_insensitive has the wrong type")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use ansi_term::Style;
+use manada::{Converter, UnitSet, file_path};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct SetLocations<'a> {
+    name: &'a str,
+    /// The file with the highest priority, as determined by `manada::file_path`
+    active: Option<PathBuf>,
+    paths: &'a [PathBuf],
+    /// Whether the set has compiled in conversions, which have the lowest priority
+    bundled: bool,
+}
+
+#[derive(Serialize)]
+struct UnitInfo<'a> {
+    name: &'a str,
+    long_name: Option<&'a str>,
+    symbol: Option<&'a str>,
+    description: Option<&'a str>,
+    aliases: Vec<&'a str>,
+    outgoing: usize,
+    incoming: usize,
+}
+
+pub fn print_sets(converter: &Converter, json: bool) {
+    let sets = converter
+        .locations()
+        .map(|(name, paths)| SetLocations {
+            name,
+            active: file_path(name).ok(),
+            paths,
+            bundled: converter.is_bundled(name),
+        })
+        .collect::<Vec<_>>();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&sets).unwrap());
+        return;
+    }
+
+    for set in sets {
+        println!("{}", Style::new().bold().paint(set.name));
+        for path in set.paths {
+            let marker = if set.active.as_ref() == Some(path) {
+                "*"
+            } else {
+                " "
+            };
+            println!("  {marker} {}", path.display());
+        }
+        if set.bundled {
+            let marker = if set.paths.is_empty() { "*" } else { " " };
+            println!("  {marker} (bundled)");
+        }
+    }
+}
+
+pub fn print_units(set: &UnitSet, json: bool) {
+    let mut units = set
+        .parsed()
+        .units()
+        .filter_map(|name| {
+            let (outgoing, incoming) = set.parsed().conversion_counts(name)?;
+            let metadata = set.parsed().metadata(name);
+            Some(UnitInfo {
+                name,
+                long_name: metadata.and_then(|metadata| metadata.long_name.as_deref()),
+                symbol: metadata.and_then(|metadata| metadata.symbol.as_deref()),
+                description: metadata.and_then(|metadata| metadata.description.as_deref()),
+                aliases: set.aliases_of(name),
+                outgoing,
+                incoming,
+            })
+        })
+        .collect::<Vec<_>>();
+    units.sort_unstable_by_key(|unit| unit.name);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&units).unwrap());
+        return;
+    }
+
+    let name_width = units
+        .iter()
+        .map(|unit| unit.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for unit in units {
+        print!(
+            "{}{:padding$}  {} out, {} in",
+            Style::new().bold().paint(unit.name),
+            "",
+            unit.outgoing,
+            unit.incoming,
+            padding = name_width - unit.name.chars().count(),
+        );
+        if let Some(long_name) = unit.long_name {
+            print!("  {long_name}");
+        }
+        if let Some(symbol) = unit.symbol {
+            print!(" [{symbol}]");
+        }
+        if !unit.aliases.is_empty() {
+            print!("  ({})", unit.aliases.join(", "));
+        }
+        println!();
+        if let Some(description) = unit.description {
+            println!(
+                "{:name_width$}  {}",
+                "",
+                Style::new().italic().paint(description)
+            );
+        }
+    }
+}
@@ -1,80 +1,363 @@
-mod calculation;
 mod cli;
-mod config;
+mod completion;
+mod format;
+mod lint;
+mod list;
 mod macros;
-mod parse;
+mod output;
+mod suggest;
 
-use clap::Parser;
-use config::NewConfigError;
-use manada::file_path;
-use parse::{ConversionError, Parsed};
-use std::fs::read_to_string;
+use ansi_term::{Colour, Style};
+use itertools::Itertools;
+use manada::{
+    ConversionError, Converter, Date, DimensionError, LoadError, NewConfigError, Quantity, UnitSet,
+    is_expression, parse_quantity, split_range, split_uncertainty,
+};
+use rust_decimal::Decimal;
+use std::{
+    fs::{read_to_string, write},
+    iter,
+    path::Path,
+};
 
 fn main() {
-    let cli::Cli {
-        unit_set,
-        value: cli::Value {
-            unit: start_unit,
-            value,
-        },
-        destination: end_unit,
-    } = cli::Cli::parse();
+    let cli = cli::Cli::parse_args();
+    let converter = Converter::discover().ignore_case(cli.ignore_case);
 
-    let conversions_file_path = file_path(&unit_set).unwrap_or_else(|e| exit!(1, "{e}"));
-    let file_content = read_to_string(&conversions_file_path).unwrap_or_else(|err| {
-        exit!(
-            1,
-            "Can't read file {} ({})",
-            conversions_file_path.display(),
-            err.kind()
-        );
-    });
+    match cli.command {
+        Some(cli::Command::List { json }) => return list::print_sets(&converter, json),
+        Some(cli::Command::Units { unit_set, json }) => {
+            let set = load(&converter, &unit_set);
+            return list::print_units(&set, json);
+        }
+        Some(cli::Command::Lint { unit_set, strict }) => {
+            let passed = run_lint(&converter, unit_set.as_deref(), strict);
+            std::process::exit(if passed { 0 } else { 1 });
+        }
+        Some(cli::Command::Fmt { files, check }) => {
+            let mut passed = true;
+            for file in files {
+                passed &= format_file(&file, check);
+            }
+            std::process::exit(if passed { 0 } else { 1 });
+        }
+        Some(cli::Command::Completions { shell }) => return completion::print_script(shell),
+        Some(cli::Command::Complete { words }) => {
+            return completion::candidates(&converter, &words)
+                .into_iter()
+                .for_each(|candidate| println!("{candidate}"));
+        }
+        None => {}
+    }
 
-    let parsed = Parsed::try_new(&file_content).unwrap_or_else(|err| {
-        err.print(conversions_file_path, &file_content);
-        std::process::exit(1);
-    });
+    let output_options = output::OutputOptions {
+        long: cli.long,
+        locale: cli.locale.clone(),
+        precision: cli
+            .precision
+            .or((cli.long || cli.locale.is_some()).then_some(output::LONG_PRECISION)),
+    };
+    let cli::Conversion {
+        unit_set,
+        value: raw_value,
+        destination: end_unit,
+    } = cli.conversion();
+    let (raw_value, uncertainty) =
+        split_uncertainty(&raw_value).unwrap_or_else(|e| exit!(1, "{e}"));
+    let (raw_value, upper) = match uncertainty {
+        Some(_) => (raw_value, None),
+        None => split_range(&raw_value).unwrap_or_else(|e| exit!(1, "{e}")),
+    };
 
-    let config = config::Config::try_new(&unit_set).map_or_else(
-        |err| match err {
-            NewConfigError::NoConfig => None,
-            NewConfigError::FileRead { path, error } => {
-                exit!(1, "Can't read file {} ({})", path.display(), error.kind())
-            }
-            NewConfigError::ParseError { path, error } => {
-                exit!(1, "Can't parse {}: {}", path.display(), error)
+    let set = match unit_set {
+        Some(unit_set) => load(&converter, &unit_set),
+        None => match infer_unit_set(&converter, &raw_value, &end_unit) {
+            Some(set) => set,
+            None => {
+                return convert_compound(
+                    &converter,
+                    &raw_value,
+                    uncertainty,
+                    upper,
+                    &end_unit,
+                    &output_options,
+                );
             }
         },
-        Some,
-    );
+    };
+    let unit_set = set.name();
 
-    let (start_unit, end_unit) = match config {
-        Some(config) => {
-            let config2 = config.clone();
-            let start = config2.get_full_unit(&start_unit).unwrap_or(start_unit);
-            let end = config.get_full_unit(&end_unit).unwrap_or(end_unit);
-            (start, end)
+    let Quantity {
+        value,
+        unit: start_unit,
+    } = set.quantity(&raw_value).unwrap_or_else(|e| exit!(1, "{e}"));
+
+    // Units of imported sets and expressions are converted using their dimensions
+    let in_set = set.contains(&start_unit) && set.contains(&end_unit);
+    if !in_set && !set.imports().is_empty() {
+        let convert = |value| set.convert_compound(value, &start_unit, &end_unit);
+        let converted = format_compound(
+            convert,
+            value,
+            uncertainty,
+            upper,
+            &end_unit,
+            &output_options,
+        );
+        match converted {
+            Ok(converted) => print_converted(&converted, set.sets_of(&start_unit, &end_unit)),
+            Err(err) => exit!(1, "{err}"),
         }
-        None => (start_unit, end_unit),
-    };
+        return;
+    }
 
-    let Some(&start) = parsed.get_node_by_name(&start_unit) else {
-        exit!(1, "There is no {start_unit} in {unit_set}");
+    let end = set.resolve(&end_unit);
+    let format = |value| output::format_quantity(value, &end, &set, &output_options);
+    let unrounded = output_options.unrounded();
+    let converted = match (uncertainty, upper) {
+        (Some(uncertainty), _) => set
+            .convert_uncertain(value, uncertainty, &start_unit, &end_unit)
+            .map(|(value, uncertainty)| {
+                output::format_uncertain(value, uncertainty, |value| {
+                    output::format_quantity(value, &end, &set, &unrounded)
+                })
+            }),
+        (None, Some(upper)) => set
+            .convert_range(value, upper, &start_unit, &end_unit)
+            .map(|(low, high)| output::format_range(low, high, format)),
+        (None, None) => set.convert(value, &start_unit, &end_unit).map(format),
     };
 
-    let converted = parsed.convert(start, &end_unit, value);
     match converted {
-        Ok(conv) => println!("{}{end_unit}", conv.normalize()),
-        Err(ConversionError::EndDoesntExist { end }) => {
-            exit!(1, "There is no {end} in \"{unit_set}\"")
-        }
-        Err(ConversionError::NoPathFound) => {
+        Ok(converted) => print_converted(&converted, [&set]),
+        Err(ConversionError::StartDoesntExist(start)) => exit!(
+            1,
+            "There is no {start} in {unit_set}{}",
+            suggest::did_you_mean(&start, set.unit_names())
+        ),
+        Err(ConversionError::EndDoesntExist(end)) => {
             exit!(
                 1,
-                "A conversion from {start_unit} to {end_unit} isn't possible."
+                "There is no {end} in \"{unit_set}\"{}",
+                suggest::did_you_mean(&end, set.unit_names())
             )
         }
+        Err(ConversionError::NoPathFound { start, end }) => {
+            exit!(1, "A conversion from {start} to {end} isn't possible.")
+        }
         // TODO: Show the entire calculation => add a substitute method for the calculations
         Err(ConversionError::CalculationFailed) => exit!(1, "The Calculation failed"),
+        Err(err @ (ConversionError::NotMonotonic { .. } | ConversionError::ReversedRange)) => {
+            exit!(1, "{err}")
+        }
+    }
+}
+
+/// Converts between unit expressions like `km/h` and `m/s` or units of different sets with the
+/// same dimension
+fn convert_compound(
+    converter: &Converter,
+    raw_value: &str,
+    uncertainty: Option<Decimal>,
+    upper: Option<Decimal>,
+    end_unit: &str,
+    output_options: &output::OutputOptions,
+) {
+    let Quantity { value, unit } =
+        parse_quantity(raw_value, iter::empty()).unwrap_or_else(|e| exit!(1, "{e}"));
+    let convert = |value| converter.convert_compound(value, &unit, end_unit);
+    let converted = format_compound(convert, value, uncertainty, upper, end_unit, output_options);
+    let converted = match converted {
+        Ok(converted) => converted,
+        Err(DimensionError::UnknownUnit(_))
+            if !is_expression(&unit) && !is_expression(end_unit) =>
+        {
+            exit!(
+                1,
+                "No unit set contains both {raw_value} and {end_unit}, please specify one"
+            )
+        }
+        Err(err) => exit!(1, "{err}"),
+    };
+    print_converted(&converted, &converter.sets_of(&unit, end_unit));
+}
+
+/// Prints a converted value with the date of the exchange rates of `sets` and warns about the
+/// outdated ones
+fn print_converted<'s>(converted: &str, sets: impl IntoIterator<Item = &'s UnitSet>) {
+    let sets = sets
+        .into_iter()
+        .filter(|set| set.parsed().rates_date().is_some())
+        .collect::<Vec<_>>();
+    let dates = sets
+        .iter()
+        .filter_map(|set| set.parsed().rates_date())
+        .sorted()
+        .dedup()
+        .join(", ");
+    if dates.is_empty() {
+        println!("{converted}");
+    } else {
+        println!("{converted} (rates of {dates})");
+    }
+
+    let today = Date::today();
+    for set in sets {
+        if let Some(age) = set.outdated_rates(today) {
+            eprintln!(
+                "{}The rates of {} are {age} days old",
+                Colour::Yellow.paint("warning: "),
+                set.name()
+            );
+        }
+    }
+}
+
+/// Converts and formats a value with an optional uncertainty or upper end of a range using
+/// `convert`. Only linear conversions can be combined, so the uncertainty can be converted on its
+/// own and a range by its ends.
+fn format_compound(
+    convert: impl Fn(Decimal) -> Result<Decimal, DimensionError>,
+    value: Decimal,
+    uncertainty: Option<Decimal>,
+    upper: Option<Decimal>,
+    end_unit: &str,
+    output_options: &output::OutputOptions,
+) -> Result<String, DimensionError> {
+    let format = |value| output::format_compound(value, end_unit, output_options);
+    let converted = convert(value)?;
+    Ok(match (uncertainty, upper) {
+        (Some(uncertainty), _) => {
+            let unrounded = output_options.unrounded();
+            output::format_uncertain(converted, convert(uncertainty)?.abs(), |value| {
+                output::format_compound(value, end_unit, &unrounded)
+            })
+        }
+        (None, Some(upper)) => {
+            let upper = convert(upper)?;
+            output::format_range(converted.min(upper), converted.max(upper), format)
+        }
+        (None, None) => format(converted),
+    })
+}
+
+/// Finds the only unit set which contains both units
+fn infer_unit_set(converter: &Converter, raw_value: &str, end_unit: &str) -> Option<UnitSet> {
+    let mut candidates = converter
+        .names()
+        .filter_map(|name| converter.load(name).ok())
+        .filter(|set| {
+            set.quantity(raw_value)
+                .is_ok_and(|quantity| set.contains(&quantity.unit) && set.contains(end_unit))
+        })
+        .collect::<Vec<_>>();
+
+    match candidates.len() {
+        0 => None,
+        1 => Some(candidates.remove(0)),
+        _ => exit!(
+            1,
+            "Multiple unit sets contain both {raw_value} and {end_unit}, please specify one of: {}",
+            candidates.iter().map(UnitSet::name).join(", ")
+        ),
+    }
+}
+
+/// Lints one or all unit sets, returns whether they passed
+fn run_lint(converter: &Converter, unit_set: Option<&str>, strict: bool) -> bool {
+    let names = match unit_set {
+        Some(name) => vec![name],
+        None => converter.names().collect(),
+    };
+
+    let mut passed = true;
+    for name in names {
+        eprintln!("{}", Style::new().bold().paint(name));
+        passed &= match lint::lint_set(converter, name) {
+            Ok(diagnostics) => lint::report(&diagnostics, strict),
+            Err(err) => {
+                print_load_error(converter, name, err);
+                false
+            }
+        };
+    }
+    passed
+}
+
+/// Formats or checks a single file, returns whether it was successful
+fn format_file(path: &Path, check: bool) -> bool {
+    let content = match read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Can't read file {} ({})", path.display(), err.kind());
+            return false;
+        }
+    };
+    let formatted = match format::format(&content) {
+        Ok(formatted) => formatted,
+        Err(err) => {
+            err.print(path.to_path_buf(), &content);
+            return false;
+        }
+    };
+
+    if formatted == content {
+        return true;
+    }
+    if !format::is_equivalent(&content, &formatted) {
+        eprintln!(
+            "Formatting {} would change its conversions, please report this",
+            path.display()
+        );
+        return false;
+    }
+    if check {
+        eprintln!("{} isn't formatted", path.display());
+        return false;
+    }
+
+    match write(path, formatted) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("Can't write file {} ({})", path.display(), err.kind());
+            false
+        }
+    }
+}
+
+fn load(converter: &Converter, unit_set: &str) -> UnitSet {
+    converter.load(unit_set).unwrap_or_else(|err| {
+        print_load_error(converter, unit_set, err);
+        std::process::exit(1);
+    })
+}
+
+fn print_load_error(converter: &Converter, unit_set: &str, err: LoadError) {
+    match err {
+        LoadError::NotFound(err) => {
+            eprintln!(
+                "{err}{}",
+                suggest::did_you_mean(unit_set, converter.names())
+            )
+        }
+        LoadError::FileRead { path, error }
+        | LoadError::Config(NewConfigError::FileRead { path, error }) => {
+            eprintln!("Can't read file {} ({})", path.display(), error.kind())
+        }
+        LoadError::Parse { source, error } => error.print(source.path, &source.content),
+        LoadError::Config(NewConfigError::ParseError { path, error }) => {
+            eprintln!("Can't parse {}: {}", path.display(), error)
+        }
+        LoadError::Config(NewConfigError::Alias(err)) | LoadError::Alias(err) => {
+            eprintln!("Invalid aliases in {unit_set}: {err}")
+        }
+        LoadError::Import { name, error } => {
+            eprintln!("Can't import {name} into {unit_set}:");
+            print_load_error(converter, &name, *error)
+        }
+        err @ (LoadError::ImportCycle(_) | LoadError::Rates { .. }) => eprintln!("{err}"),
+        LoadError::Config(NewConfigError::NoConfig) => {
+            unreachable!("a missing config isn't an error")
+        }
     }
 }
@@ -0,0 +1,225 @@
+use manada::UnitSet;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// Languages which use a comma as their decimal separator
+const DECIMAL_COMMA_LANGUAGES: [&str; 16] = [
+    "cs", "da", "de", "es", "fi", "fr", "id", "it", "nb", "nl", "pl", "pt", "ru", "sv", "tr", "uk",
+];
+
+/// The decimal places `--long` and `--locale` round to if no precision is given
+pub const LONG_PRECISION: u32 = 4;
+
+#[derive(Default, Clone)]
+pub struct OutputOptions {
+    /// Print the display name instead of the unit
+    pub long: bool,
+    /// Translate the display name and format the number for this locale, implies `long`
+    pub locale: Option<String>,
+    /// Round numbers to this many decimal places
+    pub precision: Option<u32>,
+}
+
+impl OutputOptions {
+    /// The same options without rounding, for values which are already rounded
+    pub fn unrounded(&self) -> Self {
+        Self {
+            precision: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// The tags a locale like `de_AT.UTF-8` is looked up by, the most specific one comes first
+fn locale_tags(locale: &str) -> Vec<String> {
+    let tag = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default().to_string();
+
+    if language == tag {
+        vec![tag]
+    } else {
+        vec![tag, language]
+    }
+}
+
+fn format_number(value: Decimal, tags: &[String], precision: Option<u32>) -> String {
+    let value = match precision {
+        Some(precision) => {
+            value.round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero)
+        }
+        None => value,
+    };
+    let value = value.normalize().to_string();
+    let uses_comma = tags
+        .last()
+        .is_some_and(|language| DECIMAL_COMMA_LANGUAGES.contains(&language.as_str()));
+
+    if uses_comma {
+        value.replace('.', ",")
+    } else {
+        value
+    }
+}
+
+pub fn format_quantity(
+    value: Decimal,
+    unit: &str,
+    set: &UnitSet,
+    options: &OutputOptions,
+) -> String {
+    let metadata = set.parsed().metadata(unit);
+    let tags = options
+        .locale
+        .as_deref()
+        .map(locale_tags)
+        .unwrap_or_default();
+    let number = format_number(value, &tags, options.precision);
+
+    if !options.long && options.locale.is_none() {
+        let symbol = metadata.and_then(|unit| unit.symbol.as_deref());
+        return format!("{number}{}", symbol.unwrap_or(unit));
+    }
+
+    let tags = tags.iter().map(String::as_str).collect::<Vec<_>>();
+    let plural = value.abs() != Decimal::ONE;
+    let name = set
+        .config()
+        .and_then(|config| config.display_name(unit, &tags, plural))
+        .or_else(|| metadata?.long_name.as_deref())
+        .unwrap_or(unit);
+
+    format!("{number} {name}")
+}
+
+/// Rounds the uncertainty to two significant digits and the value to the same decimal place
+fn round_to_uncertainty(value: Decimal, uncertainty: Decimal) -> (Decimal, Decimal) {
+    if uncertainty.is_zero() {
+        return (value, uncertainty);
+    }
+    let round_dp = |n: Decimal, decimal_places| {
+        n.round_dp_with_strategy(decimal_places, RoundingStrategy::MidpointAwayFromZero)
+    };
+
+    // Uncertainties of 100 and more are rounded to tens, hundreds and so on
+    let mut magnitude = Decimal::ONE;
+    let mut scaled = uncertainty;
+    while scaled >= Decimal::ONE_HUNDRED {
+        scaled /= Decimal::TEN;
+        magnitude *= Decimal::TEN;
+    }
+    if magnitude > Decimal::ONE {
+        let round = |n: Decimal| {
+            round_dp(n / magnitude, 0)
+                .checked_mul(magnitude)
+                .unwrap_or(n)
+        };
+        return (round(value), round(uncertainty));
+    }
+
+    let mut decimal_places = 0;
+    while scaled < Decimal::TEN && decimal_places < Decimal::MAX_SCALE {
+        scaled *= Decimal::TEN;
+        decimal_places += 1;
+    }
+    (
+        round_dp(value, decimal_places),
+        round_dp(uncertainty, decimal_places),
+    )
+}
+
+/// Formats a converted measurement like `3.23mi ± 0.062mi`, with `format` formatting the value and
+/// the uncertainty
+pub fn format_uncertain(
+    value: Decimal,
+    uncertainty: Decimal,
+    format: impl Fn(Decimal) -> String,
+) -> String {
+    let (value, uncertainty) = round_to_uncertainty(value, uncertainty);
+    format!("{} ± {}", format(value), format(uncertainty))
+}
+
+/// Formats a converted range like `50f .. 68f`
+pub fn format_range(low: Decimal, high: Decimal, format: impl Fn(Decimal) -> String) -> String {
+    format!("{} .. {}", format(low), format(high))
+}
+
+/// Formats the result of converting between unit expressions, which have no display names
+pub fn format_compound(value: Decimal, unit: &str, options: &OutputOptions) -> String {
+    let tags = options
+        .locale
+        .as_deref()
+        .map(locale_tags)
+        .unwrap_or_default();
+    let number = format_number(value, &tags, options.precision);
+
+    // A unit like 1/s would be read as part of the number
+    let starts_with_digit = unit.starts_with(|c: char| c.is_ascii_digit());
+    if options.long || options.locale.is_some() || starts_with_digit {
+        format!("{number} {unit}")
+    } else {
+        format!("{number}{unit}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_locale_tags() {
+        assert_eq!(locale_tags("de"), vec!["de"]);
+        assert_eq!(locale_tags("de_AT.UTF-8"), vec!["de-AT", "de"]);
+        assert_eq!(locale_tags("en-US"), vec!["en-US", "en"]);
+    }
+
+    #[test]
+    fn test_round_to_uncertainty() {
+        assert_eq!(
+            round_to_uncertainty(dec!(3.231139), dec!(0.0621371)),
+            (dec!(3.231), dec!(0.062))
+        );
+        assert_eq!(
+            round_to_uncertainty(dec!(12.345), dec!(5)),
+            (dec!(12.3), dec!(5.0))
+        );
+        assert_eq!(
+            round_to_uncertainty(dec!(1234.5), dec!(123)),
+            (dec!(1230), dec!(120))
+        );
+        assert_eq!(
+            round_to_uncertainty(dec!(98765.4), dec!(1250)),
+            (dec!(98800), dec!(1300))
+        );
+        assert_eq!(
+            round_to_uncertainty(dec!(42.1), dec!(99.5)),
+            (dec!(42), dec!(100))
+        );
+        assert_eq!(
+            round_to_uncertainty(dec!(1.23456), dec!(0)),
+            (dec!(1.23456), dec!(0))
+        );
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(dec!(1.2500), &[], None), "1.25");
+        assert_eq!(
+            format_number(dec!(1.25), &locale_tags("de_DE"), None),
+            "1,25"
+        );
+        assert_eq!(
+            format_number(dec!(1.25), &locale_tags("en_GB"), None),
+            "1.25"
+        );
+        assert_eq!(
+            format_number(dec!(1.242742384474668), &[], Some(4)),
+            "1.2427"
+        );
+        assert_eq!(format_number(dec!(2.5), &[], Some(0)), "3");
+        assert_eq!(format_number(dec!(1.5), &[], Some(4)), "1.5");
+    }
+}
@@ -1,116 +1,595 @@
-use crate::calculation::{
-    parser::{CalculationParseError, Parser},
-    token::{token_list, TokenizeError},
-    Value,
+use crate::{
+    calculation::{
+        Linearity, Operator, Value,
+        parser::{CalculationParseError, Parser},
+        token::{TokenizeError, token_list},
+    },
+    declaration::{DeclarationError, UnitMetadata, parse_declaration},
+    dimension::{DimensionError, parse_unit_expression},
+    rates::{Date, Rates},
 };
 use ansi_term::{Colour, Style};
 use derive_more::{Display, From};
 use itertools::Itertools;
-use petgraph::{algo::astar, stable_graph::NodeIndex, Directed, Graph};
+use petgraph::{
+    Direction, Graph,
+    algo::{astar, has_path_connecting},
+    stable_graph::NodeIndex,
+    unionfind::UnionFind,
+    visit::EdgeRef,
+};
 use rust_decimal::Decimal;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
 
-pub struct Parsed<'a> {
-    graph: Graph<&'a str, Value>,
-    nodes: HashMap<&'a str, NodeIndex>,
+/// The units of a set and the conversions between them, which doesn't borrow the parsed files
+#[derive(Default)]
+pub struct Parsed {
+    graph: Graph<Arc<str>, Value>,
+    nodes: HashMap<Arc<str>, NodeIndex>,
+    metadata: HashMap<Arc<str>, UnitMetadata>,
+    /// The name of the base dimension declared with `@dimension`
+    dimension: Option<String>,
+    /// The unit and unit expression declared with `@derived`
+    derived: Option<(String, String)>,
+    /// The unit sets declared with `@import`, in the order they were imported
+    imports: Vec<String>,
+    /// The rates file declared with `@rates`
+    rates: Option<String>,
+    /// The date of the rates added with [`Parsed::add_rates`]
+    rates_date: Option<Date>,
 }
 
-impl Parsed<'_> {
-    pub fn try_new(unparsed: &str) -> Result<Parsed<'_>, ParseError> {
-        let mut graph: Graph<&str, Value, Directed> = Graph::new();
-        let mut nodes = HashMap::new();
-
-        let lines = unparsed.lines().enumerate().filter_map(|(i, line)| {
-            let line = line.trim();
-            let line = line
-                .find('#')
-                .map_or(line, |comment_start| &line[0..comment_start]);
+impl Parsed {
+    pub fn try_new(unparsed: &str) -> Result<Self, ParseError> {
+        let mut parsed = Self::default();
+        parsed.try_extend(unparsed)?;
+        Ok(parsed)
+    }
 
-            (!line.is_empty()).then_some((i, line))
-        });
-        for (i, line) in lines {
-            let (origin, rest) = line.split_once(" -> ").ok_or(ParseError {
+    /// Adds the conversions of another file, conversions which already exist are replaced and
+    /// `@remove origin -> dest` removes a conversion. `@include` directives are skipped, they are
+    /// resolved when the files are read
+    pub fn try_extend(&mut self, unparsed: &str) -> Result<(), ParseError> {
+        for (i, line) in lines(unparsed) {
+            let line = parse_line(line).map_err(|error_kind| ParseError {
                 line: i,
-                error_kind: ParseErrorKind::MissingArrow,
+                error_kind,
             })?;
-            let (dest, conv) = rest.split_once(": ").ok_or(ParseError {
-                line: i,
-                error_kind: ParseErrorKind::MissingColon,
-            })?;
-            let origin = origin.trim();
-            let dest = dest.trim();
-            let conv = conv.trim();
+            match line {
+                Line::Include(_) => {}
+                Line::Dimension(name) => self.dimension = Some(name.to_string()),
+                Line::Rates(name) => self.rates = Some(name.to_string()),
+                Line::Import(name) => {
+                    if !self.imports.iter().any(|import| import == name) {
+                        self.imports.push(name.to_string());
+                    }
+                }
+                Line::Derived { unit, expression } => {
+                    self.derived = Some((unit.to_string(), expression.to_string()))
+                }
+                Line::Declaration { name, metadata } => {
+                    let node = self.node(name);
+                    let name = self.graph[node].clone();
+                    self.metadata.entry(name).or_default().merge(metadata);
+                }
+                Line::Remove { origin, dest } => self.remove_edge(origin, dest),
+                Line::Conversion {
+                    origin,
+                    dest,
+                    value,
+                } => {
+                    let origin = self.node(origin);
+                    let dest = self.node(dest);
+                    self.graph.update_edge(origin, dest, value.simplify());
+                }
+            }
+        }
 
-            let origin = *nodes
-                .entry(origin)
-                .or_insert_with(|| graph.add_node(origin));
-            let dest = nodes.entry(dest).or_insert_with(|| graph.add_node(dest));
+        Ok(())
+    }
 
-            let tokens = token_list(conv).map_err(|e| ParseError {
-                line: i,
-                error_kind: e.into(),
-            })?;
-            let mut parser = Parser::new(tokens);
-            let value = parser.parse_expression().map_err(|e| ParseError {
-                line: i,
-                error_kind: e.into(),
-            })?;
+    /// Adds conversions in both directions between the base currency and every other currency,
+    /// whose units are their lowercase codes
+    pub fn add_rates(&mut self, rates: &Rates) {
+        let base = self.node(&rates.base.to_lowercase());
+        for (currency, rate) in &rates.rates {
+            let currency = self.node(&currency.to_lowercase());
+            let rate = Value::Num(*rate);
+            self.graph.update_edge(
+                base,
+                currency,
+                Value::calc(Value::Var, Operator::Mul, rate.clone()),
+            );
+            self.graph
+                .update_edge(currency, base, Value::calc(Value::Var, Operator::Div, rate));
+        }
+        self.rates_date = Some(rates.date);
+    }
 
-            graph.add_edge(origin, *dest, value);
+    fn node(&mut self, name: &str) -> NodeIndex {
+        if let Some(&node) = self.nodes.get(name) {
+            return node;
         }
+        let name = Arc::<str>::from(name);
+        let node = self.graph.add_node(name.clone());
+        self.nodes.insert(name, node);
+        node
+    }
 
-        Ok(Parsed { graph, nodes })
+    fn remove_edge(&mut self, origin: &str, dest: &str) {
+        let (Some(&origin), Some(&dest)) = (self.nodes.get(origin), self.nodes.get(dest)) else {
+            return;
+        };
+        if let Some(edge) = self.graph.find_edge(origin, dest) {
+            self.graph.remove_edge(edge);
+        }
     }
 
-    pub fn get_node_by_name(&self, name: &str) -> Option<&NodeIndex> {
+    pub(crate) fn get_node_by_name(&self, name: &str) -> Option<&NodeIndex> {
         self.nodes.get(name)
     }
 
-    pub fn convert<'a>(
+    pub fn contains(&self, unit: &str) -> bool {
+        self.nodes.contains_key(unit)
+    }
+
+    pub fn units(&self) -> impl Iterator<Item = &str> {
+        self.nodes.keys().map(|name| &**name)
+    }
+
+    /// Every conversion as origin, destination and calculation, sorted by the units
+    pub fn conversions(&self) -> Vec<(&str, &str, &Value)> {
+        let mut conversions = self
+            .graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    &*self.graph[edge.source()],
+                    &*self.graph[edge.target()],
+                    edge.weight(),
+                )
+            })
+            .collect::<Vec<_>>();
+        conversions.sort_unstable_by_key(|&(origin, dest, _)| (origin, dest));
+        conversions
+    }
+
+    pub fn dimension(&self) -> Option<&str> {
+        self.dimension.as_deref()
+    }
+
+    /// The unit which is defined by a unit expression of other sets and that expression
+    pub fn derived(&self) -> Option<(&str, &str)> {
+        self.derived
+            .as_ref()
+            .map(|(unit, expression)| (unit.as_str(), expression.as_str()))
+    }
+
+    /// The file the exchange rates of this set are read from
+    pub fn rates(&self) -> Option<&str> {
+        self.rates.as_deref()
+    }
+
+    /// The day the added exchange rates are from
+    pub fn rates_date(&self) -> Option<Date> {
+        self.rates_date
+    }
+
+    /// The unit sets whose units can be combined with the units of this set
+    pub fn imports(&self) -> impl Iterator<Item = &str> {
+        self.imports.iter().map(String::as_str)
+    }
+
+    pub fn metadata(&self, unit: &str) -> Option<&UnitMetadata> {
+        self.metadata.get(unit)
+    }
+
+    /// All aliases declared in the conversions file together with their unit
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.metadata.iter().flat_map(|(unit, metadata)| {
+            metadata
+                .aliases
+                .iter()
+                .map(move |alias| (alias.as_str(), &**unit))
+        })
+    }
+
+    /// Groups of units which are connected by conversions in any direction, sorted by size
+    pub fn components(&self) -> Vec<Vec<&str>> {
+        let mut union_find = UnionFind::new(self.graph.node_count());
+        for edge in self.graph.edge_references() {
+            union_find.union(edge.source().index(), edge.target().index());
+        }
+
+        let mut components = HashMap::<_, Vec<_>>::new();
+        for node in self.graph.node_indices() {
+            components
+                .entry(union_find.find(node.index()))
+                .or_default()
+                .push(&*self.graph[node]);
+        }
+
+        let mut components = components
+            .into_values()
+            .map(|mut units| {
+                units.sort_unstable();
+                units
+            })
+            .collect::<Vec<_>>();
+        components.sort_unstable_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        components
+    }
+
+    /// Conversions whose destination can't be converted back to their origin
+    pub fn one_way_conversions(&self) -> Vec<(&str, &str)> {
+        let mut one_way = self
+            .graph
+            .edge_references()
+            .filter(|edge| !has_path_connecting(&self.graph, edge.target(), edge.source(), None))
+            .map(|edge| (&*self.graph[edge.source()], &*self.graph[edge.target()]))
+            .collect::<Vec<_>>();
+        one_way.sort_unstable();
+        one_way
+    }
+
+    /// The number of conversions from and to a unit
+    pub fn conversion_counts(&self, unit: &str) -> Option<(usize, usize)> {
+        let &node = self.nodes.get(unit)?;
+        let count = |direction| self.graph.edges_directed(node, direction).count();
+        Some((count(Direction::Outgoing), count(Direction::Incoming)))
+    }
+
+    /// Converts `x` from the unit `start` to `end` along the shortest path of conversions
+    pub fn convert(&self, start: &str, end: &str, x: Decimal) -> Result<Decimal, ConversionError> {
+        self.path(start, end)?
+            .into_iter()
+            .try_fold(x, |converted, calc| calc.evaluate(converted))
+            .ok_or(ConversionError::CalculationFailed)
+    }
+
+    /// How the conversion from `start` to `end` depends on the converted value
+    pub fn linearity(&self, start: &str, end: &str) -> Result<Linearity, ConversionError> {
+        let path = self.path(start, end)?;
+        if path
+            .iter()
+            .any(|calc| calc.linearity() == Linearity::Nonlinear)
+        {
+            return Ok(Linearity::Nonlinear);
+        }
+        // Offsets can cancel out like in `c -> k -> c`
+        let zero = self.convert(start, end, Decimal::ZERO);
+        Ok(if zero.is_ok_and(|zero| zero.is_zero()) {
+            Linearity::Linear
+        } else {
+            Linearity::Affine
+        })
+    }
+
+    /// Converts `x` and propagates its `uncertainty` linearly through every conversion of the
+    /// path, using the derivative of the conversion at the converted value
+    pub fn convert_uncertain(
         &self,
-        start: NodeIndex,
-        end: &'a str,
+        start: &str,
+        end: &str,
         x: Decimal,
-    ) -> Result<Decimal, ConversionError<'a>> {
+        uncertainty: Decimal,
+    ) -> Result<(Decimal, Decimal), ConversionError> {
+        self.path(start, end)?
+            .into_iter()
+            .try_fold((x, uncertainty), |(converted, uncertainty), calc| {
+                let (value, derivative) = calc.evaluate_with_derivative(converted)?;
+                Some((value, derivative.abs().checked_mul(uncertainty)?))
+            })
+            .ok_or(ConversionError::CalculationFailed)
+    }
+
+    /// Converts every value from `low` to `high`, returning the lowest and highest result. Every
+    /// conversion of the path has to be monotonic in between, so the results lie between the
+    /// converted ends.
+    pub fn convert_range(
+        &self,
+        start: &str,
+        end: &str,
+        low: Decimal,
+        high: Decimal,
+    ) -> Result<(Decimal, Decimal), ConversionError> {
+        if low > high {
+            return Err(ConversionError::ReversedRange);
+        }
+        self.path(start, end)?
+            .into_iter()
+            .try_fold((low, high), |(low, high), calc| {
+                // Fails if a divisor can be zero in between
+                calc.evaluate_interval(low, high)
+                    .ok_or(ConversionError::CalculationFailed)?;
+                if !calc.is_monotonic(low, high) {
+                    return Err(ConversionError::NotMonotonic {
+                        start: start.to_string(),
+                        end: end.to_string(),
+                    });
+                }
+                let (low, high) = (calc.evaluate(low), calc.evaluate(high));
+                let (low, high) = low.zip(high).ok_or(ConversionError::CalculationFailed)?;
+                Ok((low.min(high), low.max(high)))
+            })
+    }
+
+    /// The calculations along the shortest path from `start` to `end`
+    fn path(&self, start: &str, end: &str) -> Result<Vec<&Value>, ConversionError> {
+        let &start_node = self
+            .nodes
+            .get(start)
+            .ok_or_else(|| ConversionError::StartDoesntExist(start.to_string()))?;
         // TODO: a* is a bit too much, it works but is way more intensive then needed
-        let (_, nodes) = astar(&self.graph, start, |n| self.graph[n] == end, |_| 1, |_| 1)
-            .ok_or_else(|| {
-                if self.nodes.contains_key(end) {
-                    ConversionError::NoPathFound
-                } else {
-                    ConversionError::EndDoesntExist { end }
+        let (_, nodes) = astar(
+            &self.graph,
+            start_node,
+            |n| &*self.graph[n] == end,
+            |_| 1,
+            |_| 0,
+        )
+        .ok_or_else(|| {
+            if self.nodes.contains_key(end) {
+                ConversionError::NoPathFound {
+                    start: start.to_string(),
+                    end: end.to_string(),
                 }
-            })?;
+            } else {
+                ConversionError::EndDoesntExist(end.to_string())
+            }
+        })?;
+
+        Ok(nodes
+            .into_iter()
+            .tuple_windows()
+            .filter_map(|(n1, n2)| {
+                let edge = self.graph.find_edge(n1, n2)?;
+                self.graph.edge_weight(edge)
+            })
+            .collect())
+    }
+}
+
+/// A single line of a conversions file without its comment
+pub enum Line<'a> {
+    Include(&'a str),
+    Import(&'a str),
+    Rates(&'a str),
+    Dimension(&'a str),
+    Derived {
+        unit: &'a str,
+        expression: &'a str,
+    },
+    Remove {
+        origin: &'a str,
+        dest: &'a str,
+    },
+    Declaration {
+        name: &'a str,
+        metadata: UnitMetadata,
+    },
+    Conversion {
+        origin: &'a str,
+        dest: &'a str,
+        value: Value,
+    },
+}
 
-        let mut calculations = nodes.into_iter().tuple_windows().filter_map(|(n1, n2)| {
-            let edge = self.graph.find_edge(n1, n2)?;
-            self.graph.edge_weight(edge)
+/// Parses a trimmed line which isn't empty or a comment
+pub fn parse_line(line: &str) -> Result<Line<'_>, ParseErrorKind> {
+    if let Some(name) = line.strip_prefix("@include ") {
+        return Ok(Line::Include(name.trim()));
+    }
+    if let Some(name) = line.strip_prefix("@import ") {
+        return Ok(Line::Import(name.trim()));
+    }
+    if let Some(name) = line.strip_prefix("@rates ") {
+        return Ok(Line::Rates(name.trim()));
+    }
+    if let Some(name) = line.strip_prefix("@dimension ") {
+        return Ok(Line::Dimension(name.trim()));
+    }
+    if let Some(derived) = line.strip_prefix("@derived ") {
+        let (unit, expression) = derived
+            .split_once(" = ")
+            .ok_or(ParseErrorKind::MissingEquals)?;
+        parse_unit_expression(expression)?;
+        return Ok(Line::Derived {
+            unit: unit.trim(),
+            expression: expression.trim(),
+        });
+    }
+    if let Some(declaration) = line
+        .strip_prefix("unit ")
+        .filter(|_| !line.contains(" -> "))
+    {
+        let (name, metadata) = parse_declaration(declaration)?;
+        return Ok(Line::Declaration { name, metadata });
+    }
+    if let Some(removed) = line.strip_prefix("@remove ") {
+        let (origin, dest) = removed
+            .split_once(" -> ")
+            .ok_or(ParseErrorKind::MissingArrow)?;
+        return Ok(Line::Remove {
+            origin: origin.trim(),
+            dest: dest.trim(),
         });
+    }
+    if let Some(directive) = line.strip_prefix('@') {
+        let directive = directive.split_whitespace().next().unwrap_or_default();
+        return Err(ParseErrorKind::UnknownDirective(directive.to_string()));
+    }
 
-        calculations
-            .try_fold(x, |converted, calc| calc.evaluate(converted))
-            .ok_or(ConversionError::CalculationFailed)
+    let (origin, rest) = line
+        .split_once(" -> ")
+        .ok_or(ParseErrorKind::MissingArrow)?;
+    let (dest, conv) = rest.split_once(": ").ok_or(ParseErrorKind::MissingColon)?;
+
+    let tokens = token_list(conv.trim())?;
+    let value = Parser::new(tokens).parse_expression()?;
+
+    Ok(Line::Conversion {
+        origin: origin.trim(),
+        dest: dest.trim(),
+        value,
+    })
+}
+
+/// The lines of a file which aren't empty or comments, with their index
+fn lines(unparsed: &str) -> impl Iterator<Item = (usize, &str)> {
+    unparsed.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.trim();
+        let line = line
+            .find('#')
+            .map_or(line, |comment_start| &line[0..comment_start])
+            .trim();
+
+        (!line.is_empty()).then_some((i, line))
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub enum SourceError {
+    FileRead { path: PathBuf, error: io::Error },
+    Parse { source: Source, error: ParseError },
+}
+
+/// Reads a conversions file and all files it includes using `@include name`. Included files come
+/// before the file including them, so its conversions take precedence.
+///
+/// Names starting with `./` or `../` are relative to the including file, other names are looked up
+/// in the search directories. Every file is only included once.
+pub fn read_sources(path: &Path) -> Result<Vec<Source>, SourceError> {
+    let mut resolver = IncludeResolver::default();
+    resolver.visit(path.to_path_buf())?;
+    Ok(resolver.sources)
+}
+
+#[derive(Default)]
+struct IncludeResolver {
+    sources: Vec<Source>,
+    /// The files which are currently being included, used to detect cycles
+    stack: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+}
+
+impl IncludeResolver {
+    fn visit(&mut self, path: PathBuf) -> Result<(), SourceError> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if self.seen.contains(&canonical) {
+            return Ok(());
+        }
+
+        let content = crate::read_file(&path).map_err(|error| SourceError::FileRead {
+            path: path.clone(),
+            error,
+        })?;
+        let includes = lines(&content)
+            .filter_map(|(i, line)| Some((i, line.strip_prefix("@include ")?.trim())))
+            .map(|(i, name)| (i, name.to_string()))
+            .collect::<Vec<_>>();
+
+        self.stack.push(canonical.clone());
+        for (line, name) in includes {
+            let error = |error_kind| SourceError::Parse {
+                source: Source {
+                    path: path.clone(),
+                    content: content.clone(),
+                },
+                error: ParseError { line, error_kind },
+            };
+
+            let included = resolve_include(&path, &name)
+                .ok_or_else(|| error(ParseErrorKind::IncludeNotFound(name.clone())))?;
+            let included_canonical = included.canonicalize().unwrap_or_else(|_| included.clone());
+            if self.stack.contains(&included_canonical) {
+                return Err(error(ParseErrorKind::IncludeCycle(name)));
+            }
+            self.visit(included)?;
+        }
+        self.stack.pop();
+
+        self.seen.insert(canonical);
+        self.sources.push(Source { path, content });
+        Ok(())
     }
 }
 
-pub enum ConversionError<'a> {
-    NoPathFound,
+fn resolve_include(including: &Path, name: &str) -> Option<PathBuf> {
+    let is_relative = name.starts_with("./") || name.starts_with("../");
+    if is_relative || Path::new(name).is_absolute() {
+        let path = including.parent().unwrap_or(Path::new("")).join(name);
+        let path = path
+            .components()
+            .filter(|component| *component != Component::CurDir)
+            .collect::<PathBuf>();
+        path.is_file().then_some(path)
+    } else {
+        crate::file_path(name).ok()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Display)]
+pub enum ConversionError {
+    #[display("There is no {_0}")]
+    StartDoesntExist(String),
+    #[display("There is no {_0}")]
+    EndDoesntExist(String),
+    #[display("A conversion from {start} to {end} isn't possible")]
+    NoPathFound { start: String, end: String },
+    #[display("The calculation failed")]
     CalculationFailed,
-    EndDoesntExist { end: &'a str },
+    #[display(
+        "The conversion from {start} to {end} might change direction within the range, only ranges over which it only increases or only decreases can be converted"
+    )]
+    NotMonotonic { start: String, end: String },
+    #[display("The range ends before it starts")]
+    ReversedRange,
 }
 
-#[derive(Debug)]
+impl Error for ConversionError {}
+
+#[derive(Debug, Display)]
+#[display("{error_kind} in line {}", line + 1)]
 pub struct ParseError {
     line: usize,
     error_kind: ParseErrorKind,
 }
 
+impl Error for ParseError {}
+
 #[derive(Debug, From, Display)]
 pub enum ParseErrorKind {
     #[display("Missing arrow \" -> \" between units")]
     MissingArrow,
     #[display("Missing arrow \": \" between second unit and conversion")]
     MissingColon,
+    #[display("Missing \" = \" between the derived unit and its unit expression")]
+    MissingEquals,
+    #[display("Unknown directive @{_0}")]
+    UnknownDirective(String),
+    #[display("Can't find the included file {_0}")]
+    IncludeNotFound(String),
+    #[display("Including {_0} results in a cycle")]
+    IncludeCycle(String),
+    #[from]
+    #[display("{_0}")]
+    Declaration(DeclarationError),
+    #[from]
+    #[display("{_0}")]
+    Dimension(DimensionError),
     #[from]
     #[display("{_0}")]
     Tokenizer(TokenizeError),
@@ -119,16 +598,24 @@ pub enum ParseErrorKind {
     CalculationParseError(CalculationParseError),
 }
 
+impl Error for ParseErrorKind {}
+
 impl ParseError {
+    pub fn new(line: usize, error_kind: ParseErrorKind) -> Self {
+        Self { line, error_kind }
+    }
+
     pub fn print(self, file_path: PathBuf, file_content: &str) {
         let Self {
             ref error_kind,
             line,
         } = self;
         eprintln!("{}{error_kind}", Colour::Red.paint("error: "));
-        let line_number_width = (line as f64).log10() as usize;
+        // Lines are counted from 1 like in editors
+        let number = line + 1;
+        let line_number_width = (number as f64).log10() as usize;
         eprintln!(
-            "{:line_number_width$} {} {}:{line}",
+            "{:line_number_width$} {} {}:{number}",
             "",
             Colour::Blue.paint("-->"),
             file_path.display(),
@@ -136,7 +623,7 @@ impl ParseError {
         if let Some(line_content) = file_content.lines().nth(line) {
             eprintln!(
                 "{} {}",
-                Colour::Blue.paint(format!("{line} |")),
+                Colour::Blue.paint(format!("{number} |")),
                 line_content
             );
         }
@@ -173,3 +660,173 @@ impl ParseError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn convert(parsed: &Parsed, start: &str, end: &str, x: Decimal) -> Option<Decimal> {
+        parsed.convert(start, end, x).ok()
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut parsed = Parsed::try_new("km -> m: x * 1000\nm -> cm: x * 100").unwrap();
+        assert_eq!(convert(&parsed, "km", "cm", dec!(1)), Some(dec!(100000)));
+
+        parsed
+            .try_extend("km -> m: x * 999\n@remove m -> cm")
+            .unwrap();
+        assert_eq!(convert(&parsed, "km", "m", dec!(1)), Some(dec!(999)));
+        assert_eq!(convert(&parsed, "km", "cm", dec!(1)), None);
+    }
+
+    #[test]
+    fn test_imports() {
+        let mut parsed =
+            Parsed::try_new("@import data\n@import time\nbps -> kbps: x / 1000").unwrap();
+        parsed.try_extend("@import data").unwrap();
+        assert_eq!(parsed.imports().collect::<Vec<_>>(), vec!["data", "time"]);
+    }
+
+    #[test]
+    fn test_owned() {
+        fn assert_owned<T: Send + Sync + 'static>(_: &T) {}
+
+        let mut parsed = Parsed::try_new(&String::from("km -> m: x * 1000")).unwrap();
+        parsed
+            .try_extend(&String::from("m -> cm: x * 100"))
+            .unwrap();
+        assert_owned(&parsed);
+
+        let parsed = std::thread::spawn(move || parsed).join().unwrap();
+        assert_eq!(convert(&parsed, "km", "cm", dec!(2)), Some(dec!(200000)));
+    }
+
+    include!("test_files.rs");
+
+    #[test]
+    fn test_simplified_conversions() {
+        for (_, line) in TEST_FILES.into_iter().flat_map(lines) {
+            let Ok(Line::Conversion { value, .. }) = parse_line(line) else {
+                continue;
+            };
+            let simplified = value.clone().simplify();
+            let xs = [
+                dec!(0),
+                dec!(1),
+                dec!(-40),
+                dec!(2.5),
+                dec!(1000000),
+                dec!(0.1234567890123456789012345678),
+                dec!(-98765432109876543210.12345678),
+            ];
+            for x in xs {
+                assert_eq!(value.evaluate(x), simplified.evaluate(x), "{line}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_uncertain() {
+        let parsed = Parsed::try_new("km -> m: x * 1000\nl100km -> kml: 100 / x").unwrap();
+        assert_eq!(
+            parsed.convert_uncertain("km", "m", dec!(2), dec!(0.1)),
+            Ok((dec!(2000), dec!(100)))
+        );
+        assert_eq!(
+            parsed.convert_uncertain("l100km", "kml", dec!(5), dec!(0.5)),
+            Ok((dec!(20), dec!(2)))
+        );
+        assert_eq!(
+            parsed.convert_uncertain("l100km", "kml", dec!(0), dec!(1)),
+            Err(ConversionError::CalculationFailed)
+        );
+    }
+
+    #[test]
+    fn test_convert_range() {
+        let parsed =
+            Parsed::try_new("f -> c: (x - 32) * 5 / 9\nc -> k: x + 273.15\nl100km -> kml: 100 / x")
+                .unwrap();
+        assert_eq!(
+            parsed.convert_range("f", "k", dec!(32), dec!(212)),
+            Ok((dec!(273.15), dec!(373.15)))
+        );
+        assert_eq!(
+            parsed.convert_range("f", "k", dec!(212), dec!(32)),
+            Err(ConversionError::ReversedRange)
+        );
+        assert_eq!(
+            parsed.convert_range("l100km", "kml", dec!(5), dec!(10)),
+            Ok((dec!(10), dec!(20)))
+        );
+        assert_eq!(
+            parsed.convert_range("l100km", "kml", dec!(-5), dec!(5)),
+            Err(ConversionError::CalculationFailed)
+        );
+
+        let parsed = Parsed::try_new("a -> b: (x - 1) * (x - 1)").unwrap();
+        assert_eq!(
+            parsed.convert_range("a", "b", dec!(2), dec!(3)),
+            Ok((dec!(1), dec!(4)))
+        );
+        assert_eq!(
+            parsed.convert_range("a", "b", dec!(0), dec!(3)),
+            Err(ConversionError::NotMonotonic {
+                start: String::from("a"),
+                end: String::from("b")
+            })
+        );
+    }
+
+    #[test]
+    fn test_graph_analysis() {
+        let parsed = Parsed::try_new(
+            "km -> m: x * 1000\nm -> km: x / 1000\nm -> cm: x * 100\nf -> c: (x - 32) * 5/9",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.components(),
+            vec![vec!["cm", "km", "m"], vec!["c", "f"]]
+        );
+        assert_eq!(parsed.one_way_conversions(), vec![("f", "c"), ("m", "cm")]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = Parsed::try_new("km -> m: x * 1000\n\nm cm: x")
+            .err()
+            .unwrap();
+        assert_eq!(err.line, 2);
+        assert!(matches!(err.error_kind, ParseErrorKind::MissingArrow));
+
+        let err = Parsed::try_new("@remove km m").err().unwrap();
+        assert!(matches!(err.error_kind, ParseErrorKind::MissingArrow));
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = std::env::temp_dir().join(format!("manada-includes-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(dir.join("set"), "@include ./shared/base\nm -> cm: x * 100").unwrap();
+        std::fs::write(dir.join("shared/base"), "km -> m: x * 1000").unwrap();
+
+        let sources = read_sources(&dir.join("set")).ok().unwrap();
+        let paths = sources
+            .iter()
+            .map(|source| &source.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [&dir.join("shared/base"), &dir.join("set")]);
+
+        std::fs::write(dir.join("shared/base"), "@include ../set").unwrap();
+        let Err(SourceError::Parse { source, error }) = read_sources(&dir.join("set")) else {
+            panic!("the cycle wasn't detected");
+        };
+        assert_eq!(source.path, dir.join("shared/base"));
+        assert!(matches!(error.error_kind, ParseErrorKind::IncludeCycle(_)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+//! Exchange rates read from a dated file, e.g. the CSV files of the European Central Bank
+
+use derive_more::{Display, From};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// A day of the Gregorian calendar
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Display)]
+#[display("{year:04}-{month:02}-{day:02}")]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Parses an ISO date like `2026-10-16` or the ECB format `16 October 2026`
+    pub fn parse(date: &str) -> Result<Self, RatesError> {
+        let invalid = || RatesError::InvalidDate(date.to_string());
+        let parts = date.trim().split(['-', ' ']).collect::<Vec<_>>();
+        let (year, month, day) = match parts.as_slice() {
+            [year, month, day] if year.len() == 4 => (*year, month.parse().ok(), *day),
+            [day, month, year] => {
+                let month = month.to_lowercase();
+                let month = MONTHS.iter().position(|name| *name == month);
+                (*year, month.map(|month| month as u32 + 1), *day)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let date = Self {
+            year: year.parse().map_err(|_| invalid())?,
+            month: month.ok_or_else(invalid)?,
+            day: day.parse().map_err(|_| invalid())?,
+        };
+        let days_in_month = match date.month {
+            2 if date.is_leap_year() => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => 0,
+        };
+        if (1..=days_in_month).contains(&date.day) {
+            Ok(date)
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// The current day in UTC
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Self::from_days(seconds as i64 / 86400)
+    }
+
+    /// The number of days from this date to `other`, negative if `other` is earlier
+    pub fn days_until(self, other: Self) -> i64 {
+        other.days() - self.days()
+    }
+
+    fn is_leap_year(self) -> bool {
+        self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0)
+    }
+
+    /// The days since 1970-01-01, using the algorithm of Howard Hinnant's `days_from_civil`
+    fn days(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from((self.month + 9) % 12);
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// The inverse of [`Date::days`]
+    fn from_days(days: i64) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        Self {
+            year: (year_of_era + era * 400 + i64::from(month <= 2)) as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+}
+
+/// How many units of each currency one unit of the base currency was worth on a day
+#[derive(Debug, PartialEq)]
+pub struct Rates {
+    pub date: Date,
+    pub base: String,
+    pub rates: Vec<(String, Decimal)>,
+}
+
+#[derive(Debug, Display, From)]
+pub enum RatesError {
+    #[from]
+    #[display("{_0}")]
+    NotFound(crate::NoFilePathError),
+    #[display("There are no rates")]
+    Empty,
+    #[display("{_0} isn't a date")]
+    InvalidDate(String),
+    #[display("The rate {rate} of {currency} isn't a positive number")]
+    InvalidRate { currency: String, rate: String },
+    #[display("There is no rate for {_0}")]
+    MissingRate(String),
+    #[from]
+    #[display("{_0}")]
+    Json(serde_json::Error),
+}
+
+impl std::error::Error for RatesError {}
+
+/// The JSON format, e.g. `{"base": "EUR", "date": "2026-10-16", "rates": {"USD": 1.0812}}`
+#[derive(Deserialize)]
+struct JsonRates {
+    base: String,
+    date: String,
+    rates: BTreeMap<String, serde_json::Number>,
+}
+
+impl Rates {
+    /// Parses a `.json` file or otherwise a CSV file in the format of the ECB, whose first column
+    /// is the date and whose rates are based on the euro. Of multiple rows the first one is used,
+    /// which is the latest one in the ECB's history files.
+    pub fn parse(path: &Path, content: &str) -> Result<Self, RatesError> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Self::parse_json(content)
+        } else {
+            Self::parse_csv(content)
+        }
+    }
+
+    fn parse_json(content: &str) -> Result<Self, RatesError> {
+        let json = serde_json::from_str::<JsonRates>(content)?;
+        let rates = json
+            .rates
+            .into_iter()
+            .map(|(currency, rate)| rate_of(currency, &rate.to_string()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            date: Date::parse(&json.date)?,
+            base: json.base,
+            rates,
+        })
+    }
+
+    /// Parses the daily or history file of the ECB, only the first and newest row is used
+    fn parse_csv(content: &str) -> Result<Self, RatesError> {
+        let mut rows = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.split(',').map(str::trim).collect::<Vec<_>>());
+        let (Some(header), Some(row)) = (rows.next(), rows.next()) else {
+            return Err(RatesError::Empty);
+        };
+        let (Some(date), Some(currencies)) = (row.first(), header.get(1..)) else {
+            return Err(RatesError::Empty);
+        };
+
+        let mut rates = Vec::new();
+        // The ECB ends every line with a comma
+        for (i, currency) in currencies.iter().enumerate() {
+            if currency.is_empty() {
+                continue;
+            }
+            match row.get(i + 1) {
+                // Currencies which weren't traded on that day have no rate
+                Some(&"N/A") => {}
+                Some(rate) => rates.push(rate_of(currency.to_string(), rate)?),
+                None => return Err(RatesError::MissingRate(currency.to_string())),
+            }
+        }
+
+        Ok(Self {
+            date: Date::parse(date)?,
+            base: String::from("EUR"),
+            rates,
+        })
+    }
+}
+
+fn rate_of(currency: String, rate: &str) -> Result<(String, Decimal), RatesError> {
+    match Decimal::from_str(rate).or_else(|_| Decimal::from_scientific(rate)) {
+        Ok(value) if value.is_sign_positive() && !value.is_zero() => Ok((currency, value)),
+        _ => Err(RatesError::InvalidRate {
+            currency,
+            rate: rate.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const ECB: &str =
+        "Date, USD, JPY, RUB, GBP, \n16 October 2026, 1.0812, 162.43, N/A, 0.8311, \n";
+
+    #[test]
+    fn test_date() {
+        let date = Date {
+            year: 2026,
+            month: 10,
+            day: 16,
+        };
+        assert_eq!(Date::parse("2026-10-16").unwrap(), date);
+        assert_eq!(Date::parse("16 October 2026").unwrap(), date);
+        assert_eq!(date.to_string(), "2026-10-16");
+        for invalid in ["2026-13-01", "2026-02-29", "16 Oct 2026", "yesterday"] {
+            assert!(Date::parse(invalid).is_err(), "{invalid}");
+        }
+        assert!(Date::parse("2024-02-29").is_ok());
+
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(date.days()), date);
+        assert_eq!(Date::parse("2024-02-28").unwrap().days_until(date), 961);
+    }
+
+    #[test]
+    fn test_parse() {
+        let rates = Rates::parse(Path::new("eurofxref.csv"), ECB).unwrap();
+        assert_eq!(
+            rates,
+            Rates {
+                date: Date::parse("2026-10-16").unwrap(),
+                base: String::from("EUR"),
+                rates: vec![
+                    (String::from("USD"), dec!(1.0812)),
+                    (String::from("JPY"), dec!(162.43)),
+                    (String::from("GBP"), dec!(0.8311)),
+                ],
+            }
+        );
+
+        let json =
+            r#"{"base": "USD", "date": "2026-10-16", "rates": {"EUR": 0.9249, "CHF": 8.6e-1}}"#;
+        let rates = Rates::parse(Path::new("rates.json"), json).unwrap();
+        assert_eq!(rates.base, "USD");
+        assert_eq!(
+            rates.rates,
+            vec![
+                (String::from("CHF"), dec!(0.86)),
+                (String::from("EUR"), dec!(0.9249)),
+            ]
+        );
+
+        // The history files of the ECB list the newest rates first
+        let history =
+            "Date,USD,JPY,CYP,\n2026-10-16,1.0812,162.43,N/A,\n2026-10-15,1.0795,161.9,N/A,\n";
+        let rates = Rates::parse(Path::new("eurofxref-hist.csv"), history).unwrap();
+        assert_eq!(rates.date, Date::parse("2026-10-16").unwrap());
+        assert_eq!(
+            rates.rates,
+            vec![
+                (String::from("USD"), dec!(1.0812)),
+                (String::from("JPY"), dec!(162.43)),
+            ]
+        );
+
+        assert!(matches!(
+            Rates::parse(Path::new("rates.csv"), "Date, USD\n16 October 2026, -1"),
+            Err(RatesError::InvalidRate { .. })
+        ));
+        assert!(matches!(
+            Rates::parse(Path::new("rates.csv"), "Date, USD, GBP\n16 October 2026, 1.08"),
+            Err(RatesError::MissingRate(currency)) if currency == "GBP"
+        ));
+        assert!(matches!(
+            Rates::parse(Path::new("rates.csv"), "Date, USD"),
+            Err(RatesError::Empty)
+        ));
+    }
+}
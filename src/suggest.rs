@@ -0,0 +1,86 @@
+use itertools::Itertools;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// The candidates which are most similar to `name`, case-insensitive matches come first
+pub fn suggestions<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let lowercase = name.to_lowercase();
+    let max_distance = ((name.chars().count() + 1) / 3).max(1);
+
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .filter_map(|candidate| {
+            let distance = levenshtein(&lowercase, &candidate.to_lowercase());
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .sorted()
+        .dedup_by(|(_, a), (_, b)| a == b)
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// A hint listing the suggestions, it is empty if there are none
+pub fn did_you_mean<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    let suggestions = suggestions(name, candidates);
+    match suggestions.as_slice() {
+        [] => String::new(),
+        [suggestion] => format!("\nDid you mean \"{suggestion}\"?"),
+        [rest @ .., last] => format!(
+            "\nDid you mean {} or \"{last}\"?",
+            rest.iter().map(|s| format!("\"{s}\"")).join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("km", "km"), 0);
+        assert_eq!(levenshtein("kn", "km"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("fuß", "fus"), 1);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let units = ["km", "m", "mm", "mi", "Meter", "meter", "lightyear"];
+        assert_eq!(suggestions("METER", units), vec!["Meter", "meter"]);
+        assert_eq!(suggestions("kmm", units), vec!["km", "mm"]);
+        assert_eq!(suggestions("ligthyear", units), vec!["lightyear"]);
+        assert!(suggestions("parsec", units).is_empty());
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        assert_eq!(did_you_mean("x", ["abc"]), "");
+        assert_eq!(did_you_mean("kmm", ["km"]), "\nDid you mean \"km\"?");
+        assert_eq!(
+            did_you_mean("kmm", ["km", "mm"]),
+            "\nDid you mean \"km\" or \"mm\"?"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+// The conversion files the tests of `parse.rs` and `format.rs` run on, both include this file
+
+const TEST_FILES: [&str; 3] = [
+    include_str!("../conversions/data"),
+    include_str!("../conversions/distance"),
+    include_str!("../conversions/temperature"),
+];